@@ -0,0 +1,52 @@
+#![feature(test)]
+extern crate test;
+
+use chipcalc_native_rust::calculation::{Board, CalculationJob, CalculationResult, Config, SearchMode};
+use chipcalc_native_rust::chip::{Chip, Color};
+use chipcalc_native_rust::control::SearchController;
+use chipcalc_native_rust::matrix::MatrixRotation;
+use chipcalc_native_rust::shape::Shape;
+use chipcalc_native_rust::stat::Stat;
+use num_traits::FromPrimitive;
+use test::Bencher;
+
+fn inventory(count: usize) -> Vec<Chip> {
+	(0..count).map(| i | Chip::new(
+		i as u32,
+		Shape::from_usize(1 + (i * 7) % 39).unwrap(),
+		Color::Blue,
+		Stat::new((i % 3) as i32, (i % 5) as i32, (i % 2) as i32, (i % 4) as i32),
+		5,
+		20,
+		MatrixRotation::from_usize(i % 4).unwrap()
+	)).collect()
+}
+
+fn search(b: &mut Bencher, count: usize, search: SearchMode) {
+	let all_chips = inventory(count);
+	let canvas = Board::NameBGM71.to_canvas(Board::MAX_LEVEL);
+	let job = CalculationJob::new(canvas, &all_chips, CalculationResult::new(&canvas), Config {
+		search,
+		..Config::default()
+	});
+	b.iter(|| {
+		let mut found = 0;
+		let _ = job.calculate_with(&SearchController::new().with_node_budget(20000), &mut | _ | found += 1);
+		found
+	});
+}
+
+#[bench]
+fn first_fit_40_chips(b: &mut Bencher) {
+	search(b, 40, SearchMode::FirstFit);
+}
+
+#[bench]
+fn first_fit_400_chips(b: &mut Bencher) {
+	search(b, 400, SearchMode::FirstFit);
+}
+
+#[bench]
+fn anchor_cell_400_chips(b: &mut Bencher) {
+	search(b, 400, SearchMode::AnchorCell);
+}
@@ -0,0 +1,236 @@
+use crate::calculation::{is_rotation_allowed, CalculationJob, CalculationResult, CalculationResultChip};
+use crate::canvas::Canvas;
+use crate::chip::Chip;
+use crate::matrix::MatrixRotation;
+use crate::rng::Rng;
+use crate::score::{Scorer, ScoredResult};
+use crate::vector2::Vector2;
+
+#[derive(Clone, Copy, Debug)]
+pub struct AnnealConfig {
+	/// Same seed, same start and same job give the same moves
+	pub seed: u64,
+	/// Moves to try
+	pub iterations: u64,
+	/// Score a move may lose and still be taken about a third of the time, at the start
+	pub start_temperature: f64,
+	/// The same at the end, the temperature falls geometrically in between
+	pub end_temperature: f64
+}
+
+impl Default for AnnealConfig {
+	fn default() -> Self {
+		Self {
+			seed: 0,
+			iterations: 100000,
+			start_temperature: 20.0,
+			end_temperature: 0.1
+		}
+	}
+}
+
+/// Layout being improved, with the mask of each chip
+#[derive(Clone)]
+struct State {
+	canvas: Canvas,
+	result: CalculationResult,
+	masks: Vec<u64>
+}
+
+/// Local search from a layout: swaps chips for unused ones of the same shape, moves chips,
+/// removes and adds them, taking moves that lose score by the Metropolis rule.
+/// Only the chips past the job's base are moved, and every layout passed through
+/// is a result in `SearchMode::FirstFit`: no more than `Config::max_empty_cells` cells empty, none of them forbidden.
+pub(crate) struct Annealer<'j, 'a> {
+	job: &'j CalculationJob<'a>,
+	scorer: Scorer,
+	config: AnnealConfig,
+	/// Chips of each shape
+	by_shape: Vec<Vec<usize>>,
+	rng: Rng
+}
+
+impl <'j, 'a> Annealer<'j, 'a> {
+	pub(crate) fn new(job: &'j CalculationJob<'a>, scorer: Scorer, config: AnnealConfig) -> Self {
+		let all_chips = job.get_context().all_chips;
+		let mut by_shape = Vec::new();
+		for (chip_index, chip) in all_chips.iter().enumerate() {
+			let shape = chip.shape as usize;
+			if by_shape.len() <= shape {
+				by_shape.resize(shape + 1, Vec::new());
+			}
+			by_shape[shape].push(chip_index);
+		}
+		Self {
+			job,
+			scorer,
+			config,
+			by_shape,
+			rng: Rng::new(config.seed)
+		}
+	}
+
+	/// Best layout found from `start`, `None` if `start` isn't a result of the job: not starting
+	/// with its base, using a chip twice or in a rotation the config doesn't allow, with chips
+	/// overlapping or off the canvas, or leaving cells empty it may not.
+	pub(crate) fn improve(&mut self, start: &CalculationResult) -> Option<ScoredResult> {
+		let all_chips = self.job.get_context().all_chips;
+		let mut current = self.start(start)?;
+		let mut current_score = self.scorer.score(&current.result.calculate_stat(all_chips));
+		let mut best = self.scorer.score_result(current.result.clone(), all_chips);
+		let iterations = self.config.iterations.max(1);
+		let cooling = (self.config.end_temperature / self.config.start_temperature).powf(1.0 / iterations as f64);
+		let mut temperature = self.config.start_temperature;
+		for _ in 0..iterations {
+			temperature *= cooling;
+			let next = match self.step(&current, all_chips) {
+				Some(x) => x,
+				None => continue
+			};
+			let score = self.scorer.score(&next.result.calculate_stat(all_chips));
+			let delta = (score - current_score) as f64;
+			if delta >= 0.0 || self.rng.unit() < (delta / temperature).exp() {
+				current = next;
+				current_score = score;
+				let scored = self.scorer.score_result(current.result.clone(), all_chips);
+				if scored > best {
+					best = scored;
+				}
+			}
+		}
+		Some(best)
+	}
+
+	/// `start` placed again chip by chip on the job's canvas, so nothing it claims is trusted.
+	fn start(&self, start: &CalculationResult) -> Option<State> {
+		let context = self.job.get_context();
+		let base = self.job.get_base();
+		if start.len() < base.len() || start.iter().zip(base.iter()).any(| (a, b) | a.chip_index != b.chip_index) {
+			return None;
+		}
+		let mut state = State {
+			canvas: *self.job.get_canvas(),
+			result: base.clone(),
+			masks: vec![0; base.len()]
+		};
+		for x in &start[base.len()..] {
+			let chip = context.all_chips.get(x.chip_index)?;
+			if state.result.is_used(x.chip_index) || !is_rotation_allowed(chip, x.rotation, context.config) {
+				return None;
+			}
+			let placement = context.index.get_placements(chip, &x.rotation).iter().find(| p | p.position == x.position)?;
+			if placement.mask & state.canvas.map != 0 {
+				return None;
+			}
+			Self::place(&mut state, x.chip_index, x.position, x.rotation, placement.mask, context.all_chips);
+		}
+		if !self.settle(&mut state) {
+			return None;
+		}
+		Some(state)
+	}
+
+	/// Whether `state` is a result in `SearchMode::FirstFit`, filling in its empty cells if so.
+	fn settle(&self, state: &mut State) -> bool {
+		let config = self.job.get_config();
+		let uncovered = self.job.get_base().uncovered | !state.canvas.map;
+		if state.result.left_size > config.max_empty_cells || config.forbidden_empty & uncovered != 0 {
+			return false;
+		}
+		state.result.uncovered = uncovered;
+		true
+	}
+
+	/// `state` after a random move, `None` if the move drawn can't be made.
+	fn step(&mut self, state: &State, all_chips: &[Chip]) -> Option<State> {
+		let fixed = self.job.get_base().len();
+		let movable = state.result.len() - fixed;
+		let mut next = state.clone();
+		match self.rng.below(4) {
+			// swap a chip for an unused one of the same shape, in the same place
+			0 if movable > 0 => {
+				let i = fixed + self.rng.below(movable);
+				let x = &state.result[i];
+				let same_shape = &self.by_shape[all_chips[x.chip_index].shape as usize];
+				let chip_index = same_shape[self.rng.below(same_shape.len())];
+				if state.result.is_used(chip_index) || !is_rotation_allowed(&all_chips[chip_index], x.rotation, self.job.get_config()) {
+					return None;
+				}
+				let (position, rotation, mask) = (x.position, x.rotation, state.masks[i]);
+				Self::remove(&mut next, i, all_chips);
+				Self::place(&mut next, chip_index, position, rotation, mask, all_chips);
+			},
+			// move a chip, or put another in its place
+			1 if movable > 0 => {
+				let i = fixed + self.rng.below(movable);
+				Self::remove(&mut next, i, all_chips);
+				self.add(&mut next, all_chips)?;
+			},
+			// take a chip off
+			2 if movable > 0 => {
+				let i = fixed + self.rng.below(movable);
+				Self::remove(&mut next, i, all_chips);
+			},
+			// add an unused chip where it fits
+			3 => self.add(&mut next, all_chips)?,
+			_ => return None
+		}
+		if !self.settle(&mut next) {
+			return None;
+		}
+		Some(next)
+	}
+
+	fn remove(state: &mut State, i: usize, all_chips: &[Chip]) {
+		let x = state.result.swap_remove(i);
+		let mask = state.masks.swap_remove(i);
+		let chip = &all_chips[x.chip_index];
+		state.canvas.map &= !mask;
+		state.result.left_size += chip.get_size();
+		if chip.rotation != x.rotation {
+			state.result.correction_cost -= chip.get_correction_cost();
+		}
+	}
+
+	/// Puts a random unused chip at a random placement it fits, `None` if the one drawn fits nowhere.
+	fn add(&mut self, state: &mut State, all_chips: &[Chip]) -> Option<()> {
+		if all_chips.is_empty() {
+			return None;
+		}
+		let chip_index = self.rng.below(all_chips.len());
+		if state.result.is_used(chip_index) {
+			return None;
+		}
+		let chip = &all_chips[chip_index];
+		let config = self.job.get_config();
+		let rotation = if config.rotate {
+			MatrixRotation::ALL[self.rng.below(chip.get_max_rotation() as usize + 1)]
+		} else {
+			chip.rotation
+		};
+		let placements = self.job.get_index().get_placements(chip, &rotation);
+		if placements.is_empty() {
+			return None;
+		}
+		let offset = self.rng.below(placements.len());
+		let placement = placements[offset..].iter().chain(placements[..offset].iter())
+			.find(| x | x.mask & state.canvas.map == 0)?;
+		Self::place(state, chip_index, placement.position, rotation, placement.mask, all_chips);
+		Some(())
+	}
+
+	fn place(state: &mut State, chip_index: usize, position: Vector2<u8>, rotation: MatrixRotation, mask: u64, all_chips: &[Chip]) {
+		let chip = &all_chips[chip_index];
+		state.canvas.map |= mask;
+		state.result.left_size -= chip.get_size();
+		if chip.rotation != rotation {
+			state.result.correction_cost += chip.get_correction_cost();
+		}
+		state.result.push(CalculationResultChip {
+			chip_index,
+			position,
+			rotation
+		});
+		state.masks.push(mask);
+	}
+}
@@ -0,0 +1,72 @@
+use crate::control::SearchController;
+use crate::score::ScoredResult;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+/// Handle on a `CalculationJob::calculate_anytime` search, shared with the threads that watch it.
+/// The best layout found so far can be read at any moment, and the search stopped through
+/// `get_controller` once it is good enough.
+pub struct BestSoFar {
+	best: Mutex<Option<ScoredResult>>,
+	improvements: AtomicU64,
+	finished: AtomicBool,
+	controller: SearchController
+}
+
+impl Default for BestSoFar {
+	fn default() -> Self {
+		Self::new(SearchController::new())
+	}
+}
+
+impl BestSoFar {
+	/// `controller` limits and stops the whole search, the greedy layout included.
+	pub fn new(controller: SearchController) -> Self {
+		Self {
+			best: Mutex::new(None),
+			improvements: AtomicU64::new(0),
+			finished: AtomicBool::new(false),
+			controller
+		}
+	}
+
+	pub fn get_best(&self) -> Option<ScoredResult> {
+		self.best.lock().unwrap().clone()
+	}
+
+	pub fn get_score(&self) -> Option<i64> {
+		self.best.lock().unwrap().as_ref().map(| x | x.score)
+	}
+
+	/// Times a better layout was found, to tell cheaply whether `get_best` changed.
+	pub fn get_improvements(&self) -> u64 {
+		self.improvements.load(Ordering::Acquire)
+	}
+
+	/// Whether the search ran to the end, so no layout scores better than `get_best`.
+	pub fn is_optimal(&self) -> bool {
+		self.finished.load(Ordering::Acquire)
+	}
+
+	pub fn get_controller(&self) -> &SearchController {
+		&self.controller
+	}
+
+	/// Keeps `scored` if it is better than the best so far, returns the score to beat.
+	pub(crate) fn offer(&self, scored: ScoredResult) -> i64 {
+		let mut best = self.best.lock().unwrap();
+		match best.as_ref() {
+			Some(x) if *x >= scored => x.score,
+			_ => {
+				let score = scored.score;
+				*best = Some(scored);
+				self.improvements.fetch_add(1, Ordering::Release);
+				score
+			}
+		}
+	}
+
+	pub(crate) fn finish(&self) {
+		self.finished.store(true, Ordering::Release);
+	}
+}
@@ -0,0 +1,120 @@
+use crate::bound::StatBound;
+use crate::calculation::{is_result, prune, CalculationJob, CalculationResult};
+use crate::canvas::Canvas;
+use crate::control::SearchController;
+use crate::score::{Scorer, ScoredResult, TopK};
+use crate::search::push_children;
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+
+/// Results of `CalculationJob::calculate_beam`.
+pub struct BeamResult {
+	/// Best first
+	pub results: Vec<ScoredResult>,
+	/// Partial layouts dropped for not making the beam
+	pub dropped: u64
+}
+
+impl BeamResult {
+	/// Whether `results` are surely the best there are, the same `calculate_top` gives
+	/// in `SearchMode::AnchorCell`. Only if the beam never had to drop a partial layout.
+	pub fn is_exact(&self) -> bool {
+		self.dropped == 0
+	}
+}
+
+/// Partial layout in the beam, the one with the higher bound is better, then the one made first.
+struct Candidate {
+	bound: i64,
+	made: u64,
+	canvas: Canvas,
+	base: CalculationResult
+}
+
+impl Ord for Candidate {
+	fn cmp(&self, other: &Self) -> Ordering {
+		self.bound.cmp(&other.bound).then_with(|| other.made.cmp(&self.made))
+	}
+}
+
+impl PartialOrd for Candidate {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl PartialEq for Candidate {
+	fn eq(&self, other: &Self) -> bool {
+		self.cmp(other) == Ordering::Equal
+	}
+}
+
+impl Eq for Candidate {}
+
+/// Searches `job` one chip or skipped cell at a time, keeping only the `width` partial layouts
+/// whose `bound` scores highest at each step. `job` has to search with `bound`.
+/// If `controller` stops it, the results so far are returned, whatever `BeamResult::is_exact` says.
+pub(crate) fn beam_search(
+	job: &CalculationJob,
+	bound: &StatBound,
+	width: usize,
+	capacity: usize,
+	scorer: Scorer,
+	controller: &SearchController
+) -> BeamResult {
+	let context = job.get_context();
+	let mut top = TopK::new(capacity, scorer, context.all_chips);
+	let mut dropped = 0;
+	let mut made = 0;
+	let mut layer = Vec::new();
+	if job.is_complete() {
+		top.push(job.get_base().clone().found(job.get_canvas()));
+	} else {
+		layer.push((*job.get_canvas(), job.get_base().clone()));
+	}
+	let mut children = Vec::new();
+	while !layer.is_empty() {
+		// grows as needed, `width` may be far more than a layer ever holds
+		let mut beam = BinaryHeap::new();
+		'layer: for (canvas, base) in layer.drain(..) {
+			for class in 0..=context.classes.len() {
+				push_children(&canvas, &base, class, &context, &mut children);
+			}
+			for (canvas, base) in children.drain(..) {
+				if !controller.visit(base.len()) {
+					break 'layer;
+				}
+				// always at the end of a path, the search being `SearchMode::AnchorCell`
+				if is_result(&canvas, &base, context.config) {
+					top.push(base.found(&canvas));
+					if let Some(worst) = top.threshold() {
+						bound.raise(worst.score);
+					}
+					continue;
+				}
+				if prune(&canvas, &base, &context, controller) {
+					continue;
+				}
+				beam.push(Reverse(Candidate {
+					bound: bound.get_upper_bound(&base, canvas.get_left_space()),
+					made,
+					canvas,
+					base
+				}));
+				made += 1;
+				if beam.len() > width {
+					beam.pop();
+					dropped += 1;
+				}
+			}
+		}
+		if controller.is_stopped() {
+			break;
+		}
+		layer.extend(beam.into_sorted_vec().into_iter().map(| x | (x.0.canvas, x.0.base)));
+	}
+	BeamResult {
+		results: top.into_sorted_vec(),
+		dropped
+	}
+}
@@ -0,0 +1,85 @@
+use crate::calculation::CalculationResult;
+use crate::chip::Chip;
+use crate::score::Scorer;
+use crate::stat::Stat;
+use std::sync::atomic::{AtomicI64, Ordering};
+
+/// Branch-and-bound against the best score found so far.
+///
+/// A node's bound is its stat plus, for each stat on its own, the most the unused chips could add
+/// when filling the empty cells, taking chips with the most points per cell first and a share of
+/// the last one. Nodes whose bound scores below the incumbent can't lead to a better result.
+pub struct StatBound {
+	scorer: Scorer,
+	stats: Vec<[i32; 4]>,
+	sizes: Vec<u8>,
+	/// For each stat, the chips adding to it, most points per cell first
+	by_density: [Vec<usize>; 4],
+	incumbent: AtomicI64
+}
+
+impl StatBound {
+	pub fn new(all_chips: &[Chip], scorer: Scorer) -> Self {
+		let stats: Vec<[i32; 4]> = all_chips.iter().map(| x | x.get_stat().to_array()).collect();
+		let sizes: Vec<u8> = all_chips.iter().map(| x | x.get_size()).collect();
+		let mut by_density: [Vec<usize>; 4] = Default::default();
+		for (i, chips) in by_density.iter_mut().enumerate() {
+			*chips = (0..all_chips.len()).filter(| x | stats[*x][i] > 0).collect();
+			chips.sort_by(| a, b | (stats[*b][i] * sizes[*a] as i32).cmp(&(stats[*a][i] * sizes[*b] as i32)));
+		}
+		Self {
+			scorer,
+			stats,
+			sizes,
+			by_density,
+			incumbent: AtomicI64::new(i64::MIN)
+		}
+	}
+
+	/// Raises the score a node has to be able to reach, never lowers it.
+	pub fn raise(&self, score: i64) {
+		self.incumbent.fetch_max(score, Ordering::Relaxed);
+	}
+
+	pub fn get_incumbent(&self) -> i64 {
+		self.incumbent.load(Ordering::Relaxed)
+	}
+
+	/// Upper bound on the score of any result below `base` with `free` empty cells left to fill.
+	pub fn get_upper_bound(&self, base: &CalculationResult, free: u8) -> i64 {
+		let mut stat = [0i32; 4];
+		for x in base.iter() {
+			for (i, value) in stat.iter_mut().enumerate() {
+				*value += self.stats[x.chip_index][i];
+			}
+		}
+		let cap = self.scorer.cap.to_array();
+		for (i, value) in stat.iter_mut().enumerate() {
+			let mut room = free as i32;
+			for chip_index in &self.by_density[i] {
+				if room == 0 || *value >= cap[i] {
+					break;
+				}
+				if base.is_used(*chip_index) {
+					continue;
+				}
+				let size = self.sizes[*chip_index] as i32;
+				let points = self.stats[*chip_index][i];
+				if size <= room {
+					*value += points;
+					room -= size;
+				} else {
+					*value += (points * room + size - 1) / size;
+					room = 0;
+				}
+			}
+		}
+		self.scorer.get_upper_bound(&Stat::from_array(stat))
+	}
+
+	/// Whether nothing below `base` can score above the incumbent.
+	#[inline(always)]
+	pub fn is_bounded(&self, base: &CalculationResult, free: u8) -> bool {
+		self.get_upper_bound(base, free) < self.get_incumbent()
+	}
+}
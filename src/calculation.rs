@@ -1,20 +1,47 @@
 use crate::canvas::Canvas;
-use crate::matrix::{MatrixRotationCache, Matrix};
+use crate::matrix::Placement;
 use crate::vector2::Vector2;
 use crate::matrix::MatrixRotation::{Cw0, Cw180, Cw90, Cw270};
 use crate::matrix::MatrixRotation;
 use std::collections::VecDeque;
-use crate::chip::Chip;
+use crate::chip::{Chip, Color};
 use crate::shape::Shape;
 use strum_macros::EnumString;
+use enum_iterator::IntoEnumIterator;
+use std::cmp::Reverse;
 use std::ops::{Deref, DerefMut, Range};
 use crate::stat::Stat;
+use crate::score::{Scorer, ScoredResult, TopK};
+use crate::parallel::{calculate_parallel, ParallelConfig, ResultSink};
+use std::sync::{Arc, Mutex};
+use crate::placement::PlacementIndex;
+use crate::dlx::Dlx;
+use crate::tiling::{Assigner, ShapeTiling, Tiler};
+use crate::control::{SearchController, StopReason};
+use crate::chip_class::ChipClasses;
+use crate::bound::StatBound;
+use crate::transposition::TranspositionTable;
+use crate::num_queue::NumQueue;
+use crate::search::{Results, Search};
+use crate::stats::PruneReason;
+use crate::beam::{beam_search, BeamResult};
+use crate::anneal::{AnnealConfig, Annealer};
+use crate::anytime::BestSoFar;
+use crate::count::{count_below, LayoutCount};
+use crate::estimate::{estimate, Estimate};
 
+#[derive(Clone)]
 pub struct CalculationJob<'a> {
 	canvas: Canvas,
 	all_chips: &'a Vec<Chip>,
 	base: CalculationResult,
-	config: Config
+	config: Config,
+	index: Arc<PlacementIndex>,
+	classes: Arc<ChipClasses>,
+	/// Number of chips of each size
+	size_counts: [usize; 7],
+	bound: Option<Arc<StatBound>>,
+	transpositions: Option<Arc<TranspositionTable>>
 }
 
 impl <'a> CalculationJob<'a> {
@@ -24,12 +51,131 @@ impl <'a> CalculationJob<'a> {
 		base: CalculationResult,
 		config: Config
 	) -> Self {
+		let index = Arc::new(PlacementIndex::new(&canvas));
+		let mut classes = if config.group_identical {
+			ChipClasses::new(all_chips)
+		} else {
+			ChipClasses::singletons(all_chips.len())
+		};
+		match config.order {
+			ChipOrder::LargestFirst => classes.sort_by_key(| x | Reverse(all_chips[x.members[0]].get_size())),
+			ChipOrder::HighestStat => classes.sort_by_key(| x | Reverse(all_chips[x.members[0]].get_stat().sum())),
+			ChipOrder::Inventory | ChipOrder::FewestPlacements => ()
+		}
+		let classes = Arc::new(classes);
+		let transpositions = match config.transposition_table_size {
+			0 => None,
+			size => Some(Arc::new(TranspositionTable::new(size, all_chips.len())))
+		};
+		let mut size_counts = [0; 7];
+		for chip in all_chips {
+			size_counts[chip.get_size() as usize] += 1;
+		}
 		Self {
 			canvas,
 			all_chips,
 			base,
-			config
+			config,
+			index,
+			classes,
+			size_counts,
+			bound: None,
+			transpositions
+		}
+	}
+
+	/// Job below this one, sharing its placement index.
+	fn child(&self, canvas: Canvas, base: CalculationResult) -> Self {
+		Self {
+			canvas,
+			all_chips: self.all_chips,
+			base,
+			config: self.config,
+			index: self.index.clone(),
+			classes: self.classes.clone(),
+			size_counts: self.size_counts,
+			bound: self.bound.clone(),
+			transpositions: self.transpositions.clone()
+		}
+	}
+
+	/// The same job with an empty transposition table of its own, so a new search doesn't skip
+	/// the states an earlier one visited.
+	pub(crate) fn with_new_table(&self) -> Self {
+		Self {
+			transpositions: self.transpositions.as_ref().map(| x | Arc::new(TranspositionTable::new(x.len(), self.all_chips.len()))),
+			..self.clone()
+		}
+	}
+
+	/// The same job searching in `SearchMode::AnchorCell`, whose sub-jobs never share a layout.
+	pub(crate) fn anchored(&self) -> Self {
+		Self {
+			config: Config {
+				search: SearchMode::AnchorCell,
+				..self.config
+			},
+			..self.clone()
+		}
+	}
+
+	pub(crate) fn get_context(&self) -> Context<'_, '_> {
+		Context {
+			all_chips: self.all_chips,
+			config: &self.config,
+			index: &self.index,
+			classes: &self.classes,
+			size_counts: &self.size_counts,
+			bound: self.bound.as_deref(),
+			transpositions: self.transpositions.as_deref()
+		}
+	}
+
+	pub fn get_canvas(&self) -> &Canvas {
+		&self.canvas
+	}
+
+	pub fn get_base(&self) -> &CalculationResult {
+		&self.base
+	}
+
+	pub fn get_config(&self) -> &Config {
+		&self.config
+	}
+
+	/// Chips the search tells apart; every chip is its own class unless `Config::group_identical`.
+	pub fn get_classes(&self) -> &ChipClasses {
+		&self.classes
+	}
+
+	/// Legal placements on the canvas this job was created with.
+	pub fn get_index(&self) -> &PlacementIndex {
+		&self.index
+	}
+
+	/// Whether the base is already a result, see `is_result`.
+	pub fn is_complete(&self) -> bool {
+		is_result(&self.canvas, &self.base, &self.config)
+	}
+
+	/// Searches below the base without any job accounting, `false` if `controller` stopped it.
+	pub(crate) fn search<F: FnMut(CalculationResult)>(&self, controller: &SearchController, on_found: &mut F) -> bool {
+		match self.config.backend {
+			Backend::Backtrack => Search::sharing(self.clone()).__run(controller, on_found, false),
+			Backend::DancingLinks => self.search_dlx(controller, &mut | canvas, base | on_found(base.clone().found(canvas)), false)
+		}
+	}
+
+	/// See `Dlx::search`.
+	fn search_dlx<F: FnMut(&Canvas, &CalculationResult)>(&self, controller: &SearchController, on_found: &mut F, count_jobs: bool) -> bool {
+		if self.canvas.get_left_space() == 0 {
+			return true;
 		}
+		let context = self.get_context();
+		let mut base = self.base.clone();
+		let mut dlx = Dlx::new(&self.canvas, &base, &context);
+		controller.count_placements(dlx.get_tried(), dlx.get_row_count());
+		dlx.search(&self.canvas, &mut base, &context, controller, on_found, count_jobs)
 	}
 
 	pub fn generate_jobs(self) -> GenerateJob<'a> {
@@ -37,25 +183,205 @@ impl <'a> CalculationJob<'a> {
 	}
 
 	pub fn calculate<F: FnMut(CalculationResult)>(&self, on_found: &mut F) {
-		calculate(&self.canvas, self.all_chips, &self.base, on_found, &self.config)
+		let _ = self.calculate_with(&SearchController::new(), on_found);
+	}
+
+	/// Results of `calculate` as an iterator, searching only as far as the next result.
+	/// Backtracks even with `Backend::DancingLinks`, see `Search`, so the order may differ.
+	pub fn results(&self) -> Results<'a> {
+		Search::new(self.clone()).into_results(SearchController::new())
+	}
+
+	/// `calculate` that counts its progress in `controller` and stops when it asks to.
+	/// Each sub-job of `generate_jobs` is one top-level job, or each branch of the first cell
+	/// with `Backend::DancingLinks`.
+	pub fn calculate_with<F: FnMut(CalculationResult)>(&self, controller: &SearchController, on_found: &mut F) -> Result<(), StopReason> {
+		match self.config.backend {
+			Backend::Backtrack => Search::new(self.clone()).run(controller, on_found),
+			Backend::DancingLinks => {
+				self.search_dlx(controller, &mut | canvas, base | on_found(base.clone().found(canvas)), true);
+				controller.report();
+				controller.get_result()
+			}
+		}
+	}
+
+	/// Number of results of `calculate`, without making them.
+	pub fn count(&self) -> LayoutCount {
+		let mut count = LayoutCount::default();
+		let _ = self.count_with(&SearchController::new(), &mut count);
+		count
+	}
+
+	/// `count` that adds to `count` and stops when `controller` asks to, like `calculate_with`.
+	pub fn count_with(&self, controller: &SearchController, count: &mut LayoutCount) -> Result<(), StopReason> {
+		match self.config.backend {
+			Backend::Backtrack => {
+				controller.add_jobs(self.clone().generate_jobs().count() as u64);
+				let job = self.with_new_table();
+				count_below(&job.canvas, &mut job.base.clone(), &job.get_context(), controller, count, true);
+			},
+			Backend::DancingLinks => {
+				self.search_dlx(controller, &mut | _, base | count.add(base), true);
+			}
+		}
+		controller.report();
+		controller.get_result()
+	}
+
+	/// Size and running time of `calculate`'s search from `probes` random paths down it, see `Estimate`.
+	/// Measures the speed by searching a little first. Leaves out what the transposition table would save.
+	pub fn estimate(&self, probes: u64, seed: u64) -> Estimate {
+		let job = Self {
+			transpositions: None,
+			..self.clone()
+		};
+		estimate(&job, probes, seed)
+	}
+
+	/// Runs the whole search but only keeps the `capacity` best results by `scorer`, best first.
+	pub fn calculate_top(&self, capacity: usize, scorer: Scorer) -> Vec<ScoredResult> {
+		let mut top = TopK::new(capacity, scorer, self.all_chips);
+		self.calculate(&mut | result | top.push(result));
+		top.into_sorted_vec()
+	}
+
+	/// `calculate_top` by branch-and-bound: once `capacity` results are kept, subtrees that can't
+	/// score above the worst of them are cut, see `StatBound`. Gives the same results.
+	pub fn calculate_best(&self, capacity: usize, scorer: Scorer) -> Vec<ScoredResult> {
+		let bound = Arc::new(StatBound::new(self.all_chips, scorer));
+		let job = Self {
+			bound: Some(bound.clone()),
+			..self.clone()
+		};
+		let mut top = TopK::new(capacity, scorer, self.all_chips);
+		job.calculate(&mut | result | {
+			top.push(result);
+			if let Some(worst) = top.threshold() {
+				bound.raise(worst.score);
+			}
+		});
+		top.into_sorted_vec()
+	}
+
+	/// Approximate `calculate_top` for inventories too large to search through: keeps only the
+	/// `width` partial layouts with the best `StatBound` at each step, see `BeamResult::is_exact`.
+	/// Always searches in `SearchMode::AnchorCell`, so no layout is kept twice, and compares
+	/// to `calculate_top` in that mode, which may find more layouts than the job's own.
+	pub fn calculate_beam(&self, width: usize, capacity: usize, scorer: Scorer) -> BeamResult {
+		self.calculate_beam_with(width, capacity, scorer, &SearchController::new())
+	}
+
+	/// `calculate_beam` that stops when `controller` asks to, with the results so far.
+	fn calculate_beam_with(&self, width: usize, capacity: usize, scorer: Scorer, controller: &SearchController) -> BeamResult {
+		let bound = Arc::new(StatBound::new(self.all_chips, scorer));
+		let job = Self {
+			bound: Some(bound.clone()),
+			..self.anchored()
+		};
+		beam_search(&job, &bound, width.max(1), capacity, scorer, controller)
+	}
+
+	/// Improves `start`, a layout of this job, by local search instead of searching again, see `AnnealConfig`.
+	/// The best layout passed through, at least as good as `start`; `None` if `start` isn't a result of this job.
+	pub fn improve(&self, start: &CalculationResult, scorer: Scorer, anneal: AnnealConfig) -> Option<ScoredResult> {
+		Annealer::new(self, scorer, anneal).improve(start)
+	}
+
+	/// Finds the best layout by `scorer`, holding a layout in `best` as soon as possible: a greedy
+	/// one first, see `calculate_beam`, then every better one `calculate_best` finds on the way.
+	/// Stops when `best`'s controller asks to, otherwise `best` ends up optimal.
+	pub fn calculate_anytime(&self, scorer: Scorer, best: &BestSoFar) -> Result<(), StopReason> {
+		let controller = best.get_controller();
+		let bound = Arc::new(StatBound::new(self.all_chips, scorer));
+		if let Some(greedy) = self.calculate_beam_with(1, 1, scorer, controller).results.pop() {
+			bound.raise(best.offer(greedy));
+		}
+		controller.get_result()?;
+		let job = Self {
+			bound: Some(bound.clone()),
+			..self.clone()
+		};
+		job.calculate_with(controller, &mut | result | {
+			bound.raise(best.offer(scorer.score_result(result, self.all_chips)));
+		})?;
+		best.finish();
+		Ok(())
+	}
+
+	/// Every distinct way to tile the canvas with the shapes of the unused chips, before choosing chips.
+	pub fn calculate_tilings<F: FnMut(ShapeTiling)>(&self, on_tiling: &mut F) {
+		Tiler::new(self.all_chips, &self.base, &self.config, &self.index).tile(&self.canvas, self.base.left_size, self.base.uncovered, on_tiling)
+	}
+
+	/// Tiles the canvas with shapes first, then picks the best scoring chips for each tiling,
+	/// so chips of the same shape don't multiply the search. Keeps the `capacity` best, best first,
+	/// with at most one result per tiling.
+	pub fn calculate_two_phase(&self, capacity: usize, scorer: Scorer) -> Vec<ScoredResult> {
+		let assigner = Assigner::new(self.all_chips, &self.base, scorer, self.config);
+		let mut top = TopK::new(capacity, scorer, self.all_chips);
+		self.calculate_tilings(&mut | tiling | if let Some(scored) = assigner.assign(&tiling) {
+			top.push_scored(scored);
+		});
+		top.into_sorted_vec()
+	}
+
+	pub fn calculate_parallel<S: ResultSink>(self, parallel: &ParallelConfig, sink: &S) {
+		let _ = calculate_parallel(self, parallel, &SearchController::new(), sink);
+	}
+
+	pub fn calculate_parallel_with<S: ResultSink>(
+		self,
+		parallel: &ParallelConfig,
+		controller: &SearchController,
+		sink: &S
+	) -> Result<(), StopReason> {
+		calculate_parallel(self, parallel, controller, sink)
+	}
+
+	/// Multi-threaded `calculate_top`.
+	pub fn calculate_top_parallel(self, parallel: &ParallelConfig, capacity: usize, scorer: Scorer) -> Vec<ScoredResult> {
+		let top = Mutex::new(TopK::new(capacity, scorer, self.all_chips));
+		let _ = calculate_parallel(self, parallel, &SearchController::new(), &top);
+		top.into_inner().unwrap().into_sorted_vec()
 	}
 }
 
 pub struct GenerateJob<'a> {
 	job: CalculationJob<'a>,
-	chips: Range<usize>,
-	cache: VecDeque<CalculationJob<'a>>
+	/// See `order_classes`
+	order: Vec<usize>,
+	stages: Range<usize>,
+	skipped: bool,
+	cache: VecDeque<CalculationJob<'a>>,
+	tried: u64,
+	fitted: u64
 }
 
 impl <'a> GenerateJob<'a> {
 	pub fn new(job: CalculationJob<'a>) -> Self {
-		let chips = 0..job.all_chips.len();
+		let order = order_classes(&job.canvas, &job.base, &job.get_context());
+		let stages = 0..job.classes.len();
 		Self {
 			job,
-			chips,
-			cache: VecDeque::with_capacity(4)
+			order,
+			stages,
+			skipped: false,
+			cache: VecDeque::with_capacity(4),
+			tried: 0,
+			fitted: 0
 		}
 	}
+
+	/// Placements checked against the canvas so far.
+	pub(crate) fn get_tried(&self) -> u64 {
+		self.tried
+	}
+
+	/// Placements that fit so far, each making a job.
+	pub(crate) fn get_fitted(&self) -> u64 {
+		self.fitted
+	}
 }
 
 impl <'a> Iterator for GenerateJob<'a> {
@@ -63,36 +389,25 @@ impl <'a> Iterator for GenerateJob<'a> {
 
 	fn next(&mut self) -> Option<Self::Item> {
 		while self.cache.is_empty() {
-			if let Some(chip_index) = self.chips.next() {
-				let chip = &self.job.all_chips[chip_index];
-				if !self.job.base.is_used(chip_index) {
+			if let Some(stage) = self.stages.next() {
+				let class = class_at(&self.order, stage);
+				let job = &self.job;
+				if let Some(chip_index) = job.classes.get_classes()[class].get_next(&job.base) {
 					let cache = &mut self.cache;
-					let job = &self.job;
-					try_put(
-						&self.job.canvas,
-						chip,
-						&mut | canvas, pos, rotation | {
-							let mut base = job.base.clone();
-							base.left_size -= chip.get_size();
-							base.push(CalculationResultChip {
-								chip_index,
-								position: pos,
-								rotation
-							});
-							if chip.rotation != rotation {
-								base.correction_cost += chip.get_correction_cost();
-							}
-							cache.push_back(
-								CalculationJob::new(
-									canvas,
-									job.all_chips,
-									base,
-									job.config
-								),
-							);
-						},
-						&self.job.config
-					)
+					self.tried += put_chip(
+						&job.canvas,
+						&job.base,
+						chip_index,
+						&job.get_context(),
+						&mut | canvas, base | cache.push_back(job.child(canvas, base))
+					);
+					self.fitted += cache.len() as u64;
+				}
+			} else if !self.skipped {
+				self.skipped = true;
+				if let Some(canvas) = skip_anchor(&self.job.canvas, &self.job.base, &self.job.config) {
+					let base = self.job.base.skipped(&self.job.canvas, &canvas);
+					return Some(self.job.child(canvas, base));
 				}
 			} else {
 				return None;
@@ -103,49 +418,217 @@ impl <'a> Iterator for GenerateJob<'a> {
 	}
 }
 
+/// What stays the same across one search
+pub(crate) struct Context<'a, 'c> {
+	pub(crate) all_chips: &'a [Chip],
+	pub(crate) config: &'c Config,
+	pub(crate) index: &'c PlacementIndex,
+	pub(crate) classes: &'c ChipClasses,
+	pub(crate) size_counts: &'c [usize; 7],
+	pub(crate) bound: Option<&'c StatBound>,
+	pub(crate) transpositions: Option<&'c TranspositionTable>
+}
+
+/// Calls `on_put` with the canvas and result of every way the search mode places `all_chips[chip_index]`.
+/// Returns the number of placements tried.
 #[inline(always)]
-fn calculate<'a, F: FnMut(CalculationResult)>(
+pub(crate) fn put_chip<F: FnMut(Canvas, CalculationResult)>(
 	canvas: &Canvas,
-	all_chips: &'a Vec<Chip>,
 	base: &CalculationResult,
-	on_found: &mut F,
-	config: &Config
-) {
-	for chip_index in 0..all_chips.len() {
-		if base.is_used(chip_index) {
-			continue;
+	chip_index: usize,
+	context: &Context,
+	on_put: &mut F
+) -> u64 {
+	let chip = &context.all_chips[chip_index];
+	let mut on_fit = | canvas, position, rotation | {
+		let mut base = base.clone();
+		base.left_size -= chip.get_size();
+		base.push(CalculationResultChip {
+			chip_index,
+			position,
+			rotation
+		});
+		if chip.rotation != rotation {
+			base.correction_cost += chip.get_correction_cost();
 		}
-		let chip = &all_chips[chip_index];
-		try_put(
-			canvas,
-			chip,
-			&mut | canvas, position, rotation | {
-				let mut base = base.clone();
-				base.left_size -= chip.get_size();
-				base.push(CalculationResultChip {
-					chip_index,
-					position,
-					rotation
-				});
-				if chip.rotation != rotation {
-					base.correction_cost += chip.get_correction_cost();
-				}
-				if base.left_size < config.min_chip_size {
-					on_found(base);
-					return
+		on_put(canvas, base)
+	};
+	fit_chip(canvas, chip_index, context, &mut on_fit)
+}
+
+/// `put_chip` without making the results: calls `on_fit` with the canvas, position and rotation
+/// of every placement. Returns the number of placements tried.
+#[inline(always)]
+pub(crate) fn fit_chip<F: FnMut(Canvas, Vector2<u8>, MatrixRotation)>(
+	canvas: &Canvas,
+	chip_index: usize,
+	context: &Context,
+	on_fit: &mut F
+) -> u64 {
+	let chip = &context.all_chips[chip_index];
+	match context.config.search {
+		SearchMode::FirstFit => try_put(canvas, chip, on_fit, context),
+		SearchMode::AnchorCell => match canvas.get_anchor() {
+			Some(anchor) => try_put_anchor(canvas, chip, &anchor, on_fit, context),
+			None => 0
+		}
+	}
+}
+
+/// Classes in the order a node tries them with `ChipOrder::FewestPlacements`, the class tried
+/// at each stage. Empty for the other orders, where it is the order of `ChipClasses`.
+pub(crate) fn order_classes(canvas: &Canvas, base: &CalculationResult, context: &Context) -> Vec<usize> {
+	if context.config.order != ChipOrder::FewestPlacements {
+		return Vec::new();
+	}
+	// chips of the same shape fit as many times, unless they can't rotate
+	let mut counted: Vec<(Shape, MatrixRotation, usize)> = Vec::new();
+	let mut counts = Vec::with_capacity(context.classes.len());
+	for class in context.classes.get_classes() {
+		let count = match class.get_next(base) {
+			Some(chip_index) => {
+				let chip = &context.all_chips[chip_index];
+				let rotation = if context.config.rotate { Cw0 } else { chip.rotation };
+				match counted.iter().find(| x | x.0 == chip.shape && x.1 == rotation) {
+					Some(x) => x.2,
+					None => {
+						let count = count_fitting(canvas, chip, context);
+						counted.push((chip.shape, rotation, count));
+						count
+					}
 				}
-				calculate(&canvas, all_chips, &base, on_found, config)
 			},
-			&config
-		);
+			None => 0
+		};
+		counts.push(count);
 	}
+	let mut order: Vec<usize> = (0..counts.len()).collect();
+	order.sort_by_key(| x | counts[*x]);
+	order
 }
+
+/// Class a node tries at `stage`, past the last class none.
 #[inline(always)]
-fn try_put<F: FnMut(Canvas, Vector2<u8>, MatrixRotation)>(canvas: &Canvas, chip: &Chip, on_put: &mut F , config: &Config) {
-	let mut matrix_rotation_cache = chip.get_rotation_cache().clone();
+pub(crate) fn class_at(order: &[usize], stage: usize) -> usize {
+	order.get(stage).copied().unwrap_or(stage)
+}
+
+/// Number of placements of `chip` the search mode could make on `canvas`.
+fn count_fitting(canvas: &Canvas, chip: &Chip, context: &Context) -> usize {
+	match context.config.search {
+		SearchMode::FirstFit => {
+			let mut count = 0;
+			for_each_rotation(chip, context.config, &mut | rotation | count += context.index.get_placements(chip, &rotation)
+				.iter()
+				.filter(| x | x.mask & canvas.map == 0)
+				.count());
+			count
+		},
+		SearchMode::AnchorCell => match canvas.get_anchor() {
+			Some(anchor) => context.index.get_anchored(&anchor, chip)
+				.iter()
+				.filter(| x | is_rotation_allowed(chip, x.rotation, context.config) && x.placement.mask & canvas.map == 0)
+				.count(),
+			None => 0
+		}
+	}
+}
 
+/// Whether a node is a result: it leaves at most `Config::max_empty_cells` cells empty,
+/// none of them in `Config::forbidden_empty`. In `SearchMode::AnchorCell`, only once every cell
+/// is covered or left empty for good, so a layout isn't found again with its last cells skipped.
+#[inline(always)]
+pub(crate) fn is_result(canvas: &Canvas, base: &CalculationResult, config: &Config) -> bool {
+	match config.search {
+		SearchMode::FirstFit => base.left_size <= config.max_empty_cells && config.forbidden_empty & !canvas.map == 0,
+		SearchMode::AnchorCell => canvas.get_left_space() == 0
+	}
+}
+
+/// In `SearchMode::AnchorCell`, the canvas with the anchor cell left empty for good,
+/// if the result can still afford another empty cell and the anchor may be left empty.
+#[inline(always)]
+pub(crate) fn skip_anchor(canvas: &Canvas, base: &CalculationResult, config: &Config) -> Option<Canvas> {
+	if config.search != SearchMode::AnchorCell {
+		return None;
+	}
+	let holes = base.left_size - canvas.get_left_space();
+	if holes >= config.max_empty_cells {
+		return None;
+	}
+	let anchor = canvas.get_anchor()?;
+	if config.forbidden_empty & Canvas::get_bit(&anchor) != 0 {
+		return None;
+	}
+	let mut canvas = *canvas;
+	canvas.fill(&anchor);
+	Some(canvas)
+}
+
+/// Whether to stop at a node instead of searching below it, counted in `controller` if so.
+#[inline(always)]
+pub(crate) fn prune(canvas: &Canvas, base: &CalculationResult, context: &Context, controller: &SearchController) -> bool {
+	let reason = if context.config.prune_dead_regions && is_dead(canvas, base, context) {
+		PruneReason::DeadRegion
+	} else if context.bound.is_some_and(| x | x.is_bounded(base, canvas.get_left_space())) {
+		PruneReason::Bound
+	} else {
+		return false;
+	};
+	controller.prune(reason);
+	true
+}
+
+/// Whether the search goes on below the node: it has room left, isn't pruned and wasn't
+/// already searched from, see `prune` and `is_transposition`.
+#[inline(always)]
+pub(crate) fn is_searched(canvas: &Canvas, base: &CalculationResult, context: &Context, controller: &SearchController) -> bool {
+	canvas.get_left_space() > 0
+		&& !prune(canvas, base, context, controller)
+		&& !is_transposition(canvas, base, context, controller)
+}
+
+/// Whether the node's state was already searched from, counted in `controller` if so.
+#[inline(always)]
+pub(crate) fn is_transposition(canvas: &Canvas, base: &CalculationResult, context: &Context, controller: &SearchController) -> bool {
+	let hit = context.transpositions.is_some_and(| x | x.visit(canvas, base));
+	if hit {
+		controller.hit_transposition();
+	}
+	hit
+}
+
+/// Whether no result can be reached from `canvas`: filling each empty region as far as
+/// the sizes of the unused chips add up would still leave more cells empty than the result may.
+fn is_dead(canvas: &Canvas, base: &CalculationResult, context: &Context) -> bool {
+	let mut size_counts = *context.size_counts;
+	for x in base.iter() {
+		size_counts[context.all_chips[x.chip_index].get_size() as usize] -= 1;
+	}
+	// bit n is set if some unused chips add up to n cells
+	let mut sums = 1u128;
+	for (size, count) in size_counts.iter().enumerate().skip(1) {
+		for _ in 0..(*count).min(64 / size) {
+			sums |= sums << size;
+		}
+	}
+	let holes = base.left_size - canvas.get_left_space();
+	let mut affordable = context.config.max_empty_cells - holes;
+	for region in canvas.get_regions() {
+		let size = region.count_ones();
+		let filled = 127 - (sums & ((1u128 << (size + 1)) - 1)).leading_zeros();
+		match affordable.checked_sub((size - filled) as u8) {
+			Some(x) => affordable = x,
+			None => return true
+		}
+	}
+	false
+}
+
+#[inline(always)]
+fn for_each_rotation<F: FnMut(MatrixRotation)>(chip: &Chip, config: &Config, f: &mut F) {
 	let mut rotation = Cw0;
-	for rotation_count in 0..=(
+	for _ in 0..=(
 		if config.rotate {
 			chip.get_max_rotation()
 		} else {
@@ -153,55 +636,150 @@ fn try_put<F: FnMut(Canvas, Vector2<u8>, MatrixRotation)>(canvas: &Canvas, chip:
 			0
 		}
 	) {
-		__try_put(
-			canvas,
-			matrix_rotation_cache.get_mut(&rotation),
-			&mut |canvas, pos | on_put.call_mut((canvas, pos, rotation))
-		);
+		f(rotation);
 		rotation.rotate_cw90();
 	}
+}
 
+#[inline(always)]
+pub(crate) fn is_rotation_allowed(chip: &Chip, rotation: MatrixRotation, config: &Config) -> bool {
+	if config.rotate {
+		rotation as u8 <= chip.get_max_rotation()
+	} else {
+		rotation == chip.rotation
+	}
 }
+
 #[inline(always)]
-fn __try_put<F: FnMut(Canvas, Vector2<u8>)>(canvas: &Canvas, matrix: &mut Matrix, on_put: &mut F) {
-	for x in 0..canvas.size.x {
-		if matrix.x_size + x > canvas.size.x {
-			break;
-		}
-		for y in 0..canvas.size.y {
-			if (matrix.raw_map.len() as u8) + y > canvas.size.y {
+fn try_put<F: FnMut(Canvas, Vector2<u8>, MatrixRotation)>(canvas: &Canvas, chip: &Chip, on_put: &mut F, context: &Context) -> u64 {
+	let mut tried = 0;
+	for_each_rotation(chip, context.config, &mut | rotation | tried += __try_put(
+		canvas,
+		context.index.get_placements(chip, &rotation),
+		&mut |canvas, pos | on_put.call_mut((canvas, pos, rotation)),
+		context.config.exhaustive
+	));
+	tried
+}
+/// Puts the shape at the first of `placements` it fits, or at every one if `exhaustive`.
+/// Returns the number of placements tried.
+#[inline(always)]
+fn __try_put<F: FnMut(Canvas, Vector2<u8>)>(canvas: &Canvas, placements: &[Placement], on_put: &mut F, exhaustive: bool) -> u64 {
+	let mut tried = 0;
+	for placement in placements {
+		tried += 1;
+		if let Some(new_canvas) = canvas.put_mask(placement.mask) {
+			on_put(new_canvas, placement.position);
+			if !exhaustive {
 				break;
 			}
-			let mut new_canvas = canvas.clone();
-			let mut fit = true;
-			for i in 0..matrix.raw_map.len() {
-				if new_canvas.raw_map[i + y as usize] & matrix.raw_map[i] == 0b0 {
-					new_canvas.raw_map[i + y as usize] |= matrix.raw_map[i];
-				} else {
-					fit = false;
-					break;
-				}
-			}
-			if fit {
-				let pos = Vector2::new(x, y);
-				on_put(new_canvas, pos);
-				return;
-			}
 		}
-		matrix.shr(1);
 	}
-	return;
+	tried
+}
+
+/// Puts `chip` in every rotation whose first cell lands on `anchor`.
+#[inline(always)]
+fn try_put_anchor<F: FnMut(Canvas, Vector2<u8>, MatrixRotation)>(
+	canvas: &Canvas,
+	chip: &Chip,
+	anchor: &Vector2<u8>,
+	on_put: &mut F,
+	context: &Context
+) -> u64 {
+	let mut tried = 0;
+	for rotated in context.index.get_anchored(anchor, chip) {
+		if !is_rotation_allowed(chip, rotated.rotation, context.config) {
+			continue;
+		}
+		tried += 1;
+		if let Some(canvas) = canvas.put_mask(rotated.placement.mask) {
+			on_put(canvas, rotated.placement.position, rotated.rotation);
+		}
+	}
+	tried
+}
+
+/// How the search picks the next placements of a node.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SearchMode {
+	/// Every unused chip at its first fitting position (or every position with `Config::exhaustive`),
+	/// in every order. Reaches the same layout once per placement order.
+	FirstFit,
+	/// Covers the first empty cell in row-major order, or leaves it empty,
+	/// so every layout is reached exactly once.
+	AnchorCell
 }
 
+/// Algorithm that runs a job. Jobs split off by `CalculationJob::generate_jobs`
+/// always follow `SearchMode`, whatever the backend; `calculate_parallel` splits
+/// `DancingLinks` jobs in `SearchMode::AnchorCell`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+	/// Recursive search following `SearchMode`
+	Backtrack,
+	/// Exact cover with Algorithm X, reaching every layout `SearchMode::AnchorCell` does exactly once,
+	/// whatever `SearchMode` is. Cells are filled most constrained first.
+	DancingLinks
+}
 
+/// Order the search tries chips in at each node. Results are the same, only found in another order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChipOrder {
+	/// Order of `all_chips`
+	Inventory,
+	/// Largest shapes first
+	LargestFirst,
+	/// Chips with the fewest placements fitting the node first, counted again at every node
+	FewestPlacements,
+	/// Chips with the highest stat sum first, see `Chip::get_stat`
+	HighestStat
+}
 
 #[derive(Clone, Copy, Debug)]
 pub struct Config {
-	pub min_chip_size: u8,
-	pub rotate: bool
+	/// Most cells a result may leave empty
+	pub max_empty_cells: u8,
+	/// Cells no result may leave empty, in the layout of `Canvas::map`
+	pub forbidden_empty: u64,
+	pub rotate: bool,
+	pub search: SearchMode,
+	/// In `SearchMode::FirstFit`, try every position a chip fits at instead of only the first.
+	pub exhaustive: bool,
+	pub backend: Backend,
+	/// Search over classes of identical chips instead of single chips, so swapping identical chips
+	/// doesn't make another result. See `ChipClasses`.
+	pub group_identical: bool,
+	/// Stop at nodes whose empty regions the unused chips can't fill closely enough, see `Canvas::get_regions`.
+	pub prune_dead_regions: bool,
+	/// Slots of the `TranspositionTable` the backtracking search skips repeated states with,
+	/// 0 for none. A state is a layout, so this only skips layouts `SearchMode::FirstFit` reaches
+	/// again by placing their chips in another order; every search starts with an empty table.
+	/// Once it is too small to hold every state, which states it forgets depends on the order they
+	/// come in, so `calculate_parallel` may then find some of those layouts more often than `calculate`.
+	pub transposition_table_size: usize,
+	/// With `Backend::DancingLinks`, only orders the rows once, by the job's canvas.
+	pub order: ChipOrder
 }
 
-#[derive(Debug, EnumString)]
+impl Default for Config {
+	fn default() -> Self {
+		Self {
+			max_empty_cells: 0,
+			forbidden_empty: 0,
+			rotate: true,
+			search: SearchMode::FirstFit,
+			exhaustive: false,
+			backend: Backend::Backtrack,
+			group_identical: false,
+			prune_dead_regions: true,
+			transposition_table_size: 0,
+			order: ChipOrder::Inventory
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, EnumString, IntoEnumIterator, PartialEq, Eq)]
 pub enum Board {
 	NameBGM71,
 	NameAGS30,
@@ -213,6 +791,51 @@ pub enum Board {
 }
 
 impl Board {
+	pub const MAX_LEVEL: u8 = 5;
+
+	pub fn get_color(&self) -> Color {
+		match self {
+			Board::NameBGM71 => Color::Blue,
+			Board::NameAGS30 => Color::Orange,
+			Board::Name2B14 => Color::Orange,
+			Board::NameM2 => Color::Blue,
+			Board::NameAT4 => Color::Blue,
+			Board::NameQLZ04 => Color::Orange,
+			Board::NameMk153 => Color::Blue
+		}
+	}
+
+	/// Stat cap of the fully upgraded board
+	fn get_max_stat(&self) -> Stat {
+		match self {
+			Board::NameBGM71 => Stat::new(157, 328, 191, 45),
+			Board::NameAGS30 => Stat::new(92, 373, 257, 39),
+			Board::Name2B14 => Stat::new(227, 106, 55, 80),
+			Board::NameM2 => Stat::new(103, 30, 141, 55),
+			Board::NameAT4 => Stat::new(134, 291, 233, 46),
+			Board::NameQLZ04 => Stat::new(188, 162, 66, 125),
+			Board::NameMk153 => Stat::new(104, 186, 350, 39)
+		}
+	}
+
+	/// Stat cap at `level`, `None` for levels the board doesn't have.
+	///
+	/// Incomplete: only the fully upgraded caps are tabled so far, so this is also `None` below
+	/// `MAX_LEVEL` until the caps of the lower levels are added. A guessed cap would make
+	/// `Stat::get_completion` and `Stat::is_perfect` wrong.
+	pub fn get_stat_cap(&self, level: u8) -> Option<Stat> {
+		match level {
+			// TODO: caps of levels 1 to 4
+			Self::MAX_LEVEL => Some(self.get_max_stat()),
+			_ => None
+		}
+	}
+
+	/// `get_stat_cap` of every level, from level 1.
+	pub fn get_stat_caps(&self) -> Vec<Option<Stat>> {
+		(1..=Self::MAX_LEVEL).map(| level | self.get_stat_cap(level)).collect()
+	}
+
 	pub fn to_canvas(&self, level: u8) -> Canvas {
 		let map: [[u8; 8]; 8] = match self {
 			Board::NameBGM71 => [
@@ -302,19 +925,22 @@ impl Board {
 			canvas_base[y] = base;
 		}
 
-		Canvas {
-			size: Vector2::new(8, 8),
-			raw_map: canvas_base
-		}
+		Canvas::new(Vector2::new(8, 8), canvas_base)
 	}
 }
 
 
+/// Chips placed so far, in placement order, read through `Deref` or `get_chips`. Changed through
+/// `push`, `pop` and `swap_remove`, or any way through `get_chips_mut`, all keeping the set of used chips in step.
 #[derive(Clone)]
 pub struct CalculationResult {
-	pub chips: Vec<CalculationResultChip>,
+	chips: Vec<CalculationResultChip>,
+	used: NumQueue,
 	pub correction_cost: usize,
-	pub left_size: u8
+	pub left_size: u8,
+	/// Cells left empty, in the layout of `Canvas::map`: those skipped so far while searching,
+	/// and in a result, every cell no chip covers
+	pub uncovered: u64
 }
 
 impl Deref for CalculationResult {
@@ -325,32 +951,108 @@ impl Deref for CalculationResult {
 	}
 }
 
-impl DerefMut for CalculationResult {
-	fn deref_mut(&mut self) -> &mut Self::Target {
-		&mut self.chips
-	}
-}
-
 impl CalculationResult {
 	pub fn new(canvas: &Canvas) -> Self {
 		Self {
 			chips: Vec::with_capacity(8),
+			used: NumQueue::default(),
 			correction_cost: 0,
-			left_size: canvas.get_left_space()
+			left_size: canvas.get_left_space(),
+			uncovered: 0
 		}
 	}
 
-	pub fn calculate_stat(&self, all_chips: Vec<Chip>) -> Stat {
+	/// The result going from `canvas` to `skipped`, which only leaves cells empty.
+	pub(crate) fn skipped(&self, canvas: &Canvas, skipped: &Canvas) -> Self {
+		let mut result = self.clone();
+		result.uncovered |= skipped.map & !canvas.map;
+		result
+	}
+
+	/// The result found at `canvas`, with its empty cells in `uncovered`.
+	#[inline(always)]
+	pub(crate) fn found(mut self, canvas: &Canvas) -> Self {
+		self.uncovered |= !canvas.map;
+		self
+	}
+
+	/// Takes the chips by value, as a `Vec`, or by reference.
+	pub fn calculate_stat<C: AsRef<[Chip]>>(&self, all_chips: C) -> Stat {
+		let all_chips = all_chips.as_ref();
 		let mut stat = Stat::default();
 		for x in &self.chips {
-			stat += all_chips.get(x.chip_index).unwrap().get_stat();
+			stat += all_chips[x.chip_index].get_stat();
 		}
 		stat
 	}
 
+	pub fn get_chips(&self) -> &[CalculationResultChip] {
+		&self.chips
+	}
+
+	/// The chips as a `Vec` to change freely; the used chips are worked out again once it is dropped.
+	pub fn get_chips_mut(&mut self) -> ChipsMut<'_> {
+		ChipsMut {
+			result: self
+		}
+	}
+
 	#[inline(always)]
 	pub fn is_used(&self, chip_index: usize) -> bool {
-		self.iter().find(| x | x.chip_index == chip_index).is_some()
+		self.used.contains(chip_index)
+	}
+
+	pub fn get_used(&self) -> &NumQueue {
+		&self.used
+	}
+
+	#[inline(always)]
+	pub fn push(&mut self, chip: CalculationResultChip) {
+		self.used.push(chip.chip_index);
+		self.chips.push(chip);
+	}
+
+	#[inline(always)]
+	pub fn pop(&mut self) -> Option<CalculationResultChip> {
+		let chip = self.chips.pop()?;
+		self.used.rm(chip.chip_index);
+		Some(chip)
+	}
+
+	/// Removes the chip at `index`, moving the last chip in its place.
+	pub fn swap_remove(&mut self, index: usize) -> CalculationResultChip {
+		let chip = self.chips.swap_remove(index);
+		self.used.rm(chip.chip_index);
+		chip
+	}
+}
+
+/// Mutable access to the chips of a `CalculationResult`, see `CalculationResult::get_chips_mut`.
+pub struct ChipsMut<'r> {
+	result: &'r mut CalculationResult
+}
+
+impl Deref for ChipsMut<'_> {
+	type Target = Vec<CalculationResultChip>;
+
+	fn deref(&self) -> &Self::Target {
+		&self.result.chips
+	}
+}
+
+impl DerefMut for ChipsMut<'_> {
+	fn deref_mut(&mut self) -> &mut Self::Target {
+		&mut self.result.chips
+	}
+}
+
+impl Drop for ChipsMut<'_> {
+	fn drop(&mut self) {
+		let mut used = NumQueue::default();
+		for x in &self.result.chips {
+			used.push(x.chip_index);
+		}
+		self.result.used = used;
 	}
 }
 
@@ -1,19 +1,145 @@
 use crate::vector2::Vector2;
+use crate::matrix::Matrix;
 
-#[derive(Clone)]
+/// Cells with x = 0, and with x = 7
+const FIRST_COLUMN: u64 = 0x8080808080808080;
+const LAST_COLUMN: u64 = 0x0101010101010101;
+
+/// Board of up to 8x8 cells, one bit per cell, row-major from the most significant bit.
+/// A set bit is a filled cell; cells outside `size` are always filled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct Canvas {
 	pub size: Vector2<u8>,
-	pub raw_map: Vec<u8>
+	pub map: u64
 }
 
 impl Canvas {
+	/// `raw_map` holds one byte per row, the most significant bit is the leftmost cell.
+	/// Panics if `size` is over 8x8 or `raw_map` has fewer than `size.y` rows.
+	pub fn new(size: Vector2<u8>, raw_map: Vec<u8>) -> Self {
+		assert!(size.x <= 8 && size.y <= 8, "a canvas is at most 8x8, not {}x{}", size.x, size.y);
+		assert!(raw_map.len() >= size.y as usize, "{} rows in a canvas {} high", raw_map.len(), size.y);
+		let outside = 0xffu8.checked_shr(size.x as u32).unwrap_or(0);
+		let mut map = !0u64;
+		for y in 0..size.y {
+			map &= !(0xffu64 << Self::row_shift(y));
+			map |= ((raw_map[y as usize] | outside) as u64) << Self::row_shift(y);
+		}
+		Self {
+			size,
+			map
+		}
+	}
+
+	#[inline(always)]
+	fn row_shift(y: u8) -> u32 {
+		56 - 8 * y as u32
+	}
+
+	#[inline(always)]
+	pub fn get_bit(pos: &Vector2<u8>) -> u64 {
+		1 << (63 - (pos.y * 8 + pos.x))
+	}
+
+	pub fn get_raw_map(&self) -> Vec<u8> {
+		(0..self.size.y).map(| y | (self.map >> Self::row_shift(y)) as u8).collect()
+	}
+
 	pub fn get_left_space(&self) -> u8 {
-		let mut left_space: u8 = 0;
-		for x in &self.raw_map {
-			for i in 0..8 {
-				left_space += ((!x >> i) & 0x00000001);
+		(!self.map).count_ones() as u8
+	}
+
+	pub fn is_filled(&self, pos: &Vector2<u8>) -> bool {
+		self.map & Self::get_bit(pos) != 0
+	}
+
+	pub fn fill(&mut self, pos: &Vector2<u8>) {
+		self.map |= Self::get_bit(pos);
+	}
+
+	/// First empty cell in row-major order.
+	pub fn get_anchor(&self) -> Option<Vector2<u8>> {
+		let empty = !self.map;
+		if empty == 0 {
+			return None;
+		}
+		let i = empty.leading_zeros() as u8;
+		Some(Vector2::new(i % 8, i / 8))
+	}
+
+	/// Connected areas of empty cells, as masks in the layout of `map`.
+	pub fn get_regions(&self) -> Regions {
+		Regions {
+			empty: !self.map
+		}
+	}
+
+	/// The canvas with the cells of `mask` filled, if they are all empty.
+	#[inline(always)]
+	pub fn put_mask(&self, mask: u64) -> Option<Canvas> {
+		if self.map & mask != 0 {
+			return None;
+		}
+		Some(Canvas {
+			size: self.size,
+			map: self.map | mask
+		})
+	}
+
+	/// The canvas with `matrix` put at `pos`, if it fits there.
+	pub fn put(&self, matrix: &Matrix, pos: &Vector2<u8>) -> Option<Canvas> {
+		self.put_mask(matrix.to_mask().at(pos)?)
+	}
+}
+
+/// A `Canvas` the way it used to be built, one byte per row: `RawCanvas { size, raw_map }.into()`
+/// makes the canvas `Canvas { size, raw_map }` did.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RawCanvas {
+	pub size: Vector2<u8>,
+	pub raw_map: Vec<u8>
+}
+
+impl From<RawCanvas> for Canvas {
+	fn from(raw: RawCanvas) -> Self {
+		Canvas::new(raw.size, raw.raw_map)
+	}
+}
+
+impl From<Canvas> for RawCanvas {
+	fn from(canvas: Canvas) -> Self {
+		Self {
+			size: canvas.size,
+			raw_map: canvas.get_raw_map()
+		}
+	}
+}
+
+/// Iterates the connected empty regions of a `Canvas`, the one holding the anchor first.
+pub struct Regions {
+	empty: u64
+}
+
+impl Iterator for Regions {
+	type Item = u64;
+
+	fn next(&mut self) -> Option<u64> {
+		if self.empty == 0 {
+			return None;
+		}
+		let mut region = 1u64 << (63 - self.empty.leading_zeros());
+		loop {
+			let grown = (region
+				| (region >> 1 & !FIRST_COLUMN)
+				| (region << 1 & !LAST_COLUMN)
+				| region >> 8
+				| region << 8) & self.empty;
+			if grown == region {
+				break;
 			}
+			region = grown;
 		}
-		left_space
+		self.empty &= !region;
+		Some(region)
 	}
-}
\ No newline at end of file
+}
@@ -108,7 +108,7 @@ impl Deref for Chip {
 	}
 }
 
-#[derive(FromPrimitive, Clone, Copy)]
+#[derive(FromPrimitive, Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Color {
 	Orange = 1,
 	Blue = 2
@@ -0,0 +1,104 @@
+use crate::calculation::CalculationResult;
+use crate::chip::Chip;
+
+/// Chips that look the same in any result: same shape, color, rank, level, `pt` and rotation.
+#[derive(Clone, Debug)]
+pub struct ChipClass {
+	pub members: Vec<usize>
+}
+
+impl ChipClass {
+	pub fn get_count(&self) -> usize {
+		self.members.len()
+	}
+
+	/// The member to place next: members are always used in order, so that swapping two of them
+	/// never makes another result.
+	#[inline(always)]
+	pub fn get_next(&self, base: &CalculationResult) -> Option<usize> {
+		self.members.iter().find(| x | !base.is_used(**x)).copied()
+	}
+}
+
+pub struct ChipClasses {
+	classes: Vec<ChipClass>,
+	class_of: Vec<usize>
+}
+
+impl ChipClasses {
+	/// Groups identical chips, classes in the order of their first member until `sort_by_key`.
+	pub fn new(all_chips: &[Chip]) -> Self {
+		let mut classes: Vec<ChipClass> = Vec::new();
+		let mut class_of = Vec::with_capacity(all_chips.len());
+		for (chip_index, chip) in all_chips.iter().enumerate() {
+			let found = classes.iter().position(| x | Self::is_identical(&all_chips[x.members[0]], chip));
+			match found {
+				Some(class) => {
+					classes[class].members.push(chip_index);
+					class_of.push(class);
+				},
+				None => {
+					class_of.push(classes.len());
+					classes.push(ChipClass {
+						members: vec![chip_index]
+					});
+				}
+			}
+		}
+		Self {
+			classes,
+			class_of
+		}
+	}
+
+	/// Every chip in a class of its own.
+	pub fn singletons(count: usize) -> Self {
+		Self {
+			classes: (0..count).map(| x | ChipClass {
+				members: vec![x]
+			}).collect(),
+			class_of: (0..count).collect()
+		}
+	}
+
+	/// Reorders the classes, keeping the order of those with equal keys.
+	pub fn sort_by_key<K: Ord, F: FnMut(&ChipClass) -> K>(&mut self, f: F) {
+		self.classes.sort_by_key(f);
+		for (class, x) in self.classes.iter().enumerate() {
+			for chip_index in &x.members {
+				self.class_of[*chip_index] = class;
+			}
+		}
+	}
+
+	fn is_identical(a: &Chip, b: &Chip) -> bool {
+		a.shape == b.shape
+			&& a.color == b.color
+			&& a.rank == b.rank
+			&& a.level == b.level
+			&& a.pt == b.pt
+			&& a.rotation == b.rotation
+	}
+
+	pub fn get_classes(&self) -> &[ChipClass] {
+		&self.classes
+	}
+
+	pub fn get_class(&self, chip_index: usize) -> &ChipClass {
+		&self.classes[self.class_of[chip_index]]
+	}
+
+	pub fn len(&self) -> usize {
+		self.classes.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.classes.is_empty()
+	}
+
+	/// Whether `chip_index` is the member of its class to place next.
+	#[inline(always)]
+	pub fn is_next(&self, chip_index: usize, base: &CalculationResult) -> bool {
+		self.get_class(chip_index).get_next(base) == Some(chip_index)
+	}
+}
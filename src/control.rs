@@ -0,0 +1,246 @@
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering};
+use std::time::{Duration, Instant};
+use crate::stats::{PruneReason, SearchStats, StatsCounter};
+
+/// Clock is only read every this many nodes
+const DEADLINE_CHECK_INTERVAL: u64 = 256;
+
+type ProgressCallback = Box<dyn Fn(&Progress) + Send + Sync>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopReason {
+	Cancelled = 1,
+	TimeLimit = 2,
+	NodeBudget = 3
+}
+
+impl StopReason {
+	fn from_u8(value: u8) -> Option<Self> {
+		match value {
+			1 => Some(StopReason::Cancelled),
+			2 => Some(StopReason::TimeLimit),
+			3 => Some(StopReason::NodeBudget),
+			_ => None
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Progress {
+	pub nodes: u64,
+	pub results: u64,
+	/// Nodes found to be dead ends and not searched below
+	pub pruned: u64,
+	/// Nodes skipped for reaching a state the transposition table already holds
+	pub transposition_hits: u64,
+	pub jobs_done: u64,
+	pub jobs_total: u64
+}
+
+impl Progress {
+	/// Estimated share of the search already done, from the top-level jobs completed.
+	pub fn get_fraction(&self) -> f32 {
+		if self.jobs_total == 0 {
+			return 0.0;
+		}
+		self.jobs_done as f32 / self.jobs_total as f32
+	}
+}
+
+/// Passed alongside `Config` to stop a search early and watch it run.
+/// Shared by reference, so it can be cancelled from another thread.
+pub struct SearchController {
+	stop: AtomicU8,
+	time_limit: Option<Duration>,
+	/// When the first search with this controller started
+	started: OnceLock<Instant>,
+	node_budget: Option<u64>,
+	nodes: AtomicU64,
+	results: AtomicU64,
+	pruned: AtomicU64,
+	transposition_hits: AtomicU64,
+	jobs_done: AtomicU64,
+	jobs_total: AtomicU64,
+	progress_interval: u64,
+	on_progress: Option<ProgressCallback>,
+	reporting: AtomicBool,
+	stats: Option<StatsCounter>
+}
+
+impl Default for SearchController {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl SearchController {
+	pub fn new() -> Self {
+		Self {
+			stop: AtomicU8::new(0),
+			time_limit: None,
+			started: OnceLock::new(),
+			node_budget: None,
+			nodes: AtomicU64::new(0),
+			results: AtomicU64::new(0),
+			pruned: AtomicU64::new(0),
+			transposition_hits: AtomicU64::new(0),
+			jobs_done: AtomicU64::new(0),
+			jobs_total: AtomicU64::new(0),
+			progress_interval: 0,
+			on_progress: None,
+			reporting: AtomicBool::new(false),
+			stats: None
+		}
+	}
+
+	/// Stops the search `limit` after it starts, not after the controller is made. The clock
+	/// starts with the first search and keeps running while the controller is passed to the next.
+	pub fn with_time_limit(mut self, limit: Duration) -> Self {
+		self.time_limit = Some(limit);
+		self
+	}
+
+	pub fn with_node_budget(mut self, budget: u64) -> Self {
+		self.node_budget = Some(budget);
+		self
+	}
+
+	/// Calls `on_progress` every `interval` nodes and once more when the search ends.
+	pub fn with_progress<F: Fn(&Progress) + Send + Sync + 'static>(mut self, interval: u64, on_progress: F) -> Self {
+		self.progress_interval = interval.max(1);
+		self.on_progress = Some(Box::new(on_progress));
+		self
+	}
+
+	/// Keeps the counters of `get_stats`, at some cost per node.
+	pub fn with_stats(mut self) -> Self {
+		self.stats = Some(StatsCounter::new());
+		self
+	}
+
+	pub fn cancel(&self) {
+		self.stop_with(StopReason::Cancelled);
+	}
+
+	pub fn is_stopped(&self) -> bool {
+		self.stop.load(Ordering::Relaxed) != 0
+	}
+
+	pub fn get_stop_reason(&self) -> Option<StopReason> {
+		StopReason::from_u8(self.stop.load(Ordering::Relaxed))
+	}
+
+	pub fn get_progress(&self) -> Progress {
+		Progress {
+			nodes: self.nodes.load(Ordering::Relaxed),
+			results: self.results.load(Ordering::Relaxed),
+			pruned: self.pruned.load(Ordering::Relaxed),
+			transposition_hits: self.transposition_hits.load(Ordering::Relaxed),
+			jobs_done: self.jobs_done.load(Ordering::Relaxed),
+			jobs_total: self.jobs_total.load(Ordering::Relaxed)
+		}
+	}
+
+	/// `None` unless made `with_stats`.
+	pub fn get_stats(&self) -> Option<SearchStats> {
+		let stats = self.stats.as_ref()?;
+		Some(stats.get_stats(self.results.load(Ordering::Relaxed), self.transposition_hits.load(Ordering::Relaxed)))
+	}
+
+	fn stop_with(&self, reason: StopReason) {
+		let _ = self.stop.compare_exchange(0, reason as u8, Ordering::Relaxed, Ordering::Relaxed);
+	}
+
+	/// Counts a search node with `depth` chips placed, returns `false` once the search should stop.
+	pub(crate) fn visit(&self, depth: usize) -> bool {
+		if self.is_stopped() {
+			return false;
+		}
+		if let Some(stats) = &self.stats {
+			stats.visit(depth);
+		}
+		let nodes = self.nodes.fetch_add(1, Ordering::Relaxed) + 1;
+		if let Some(budget) = self.node_budget {
+			if nodes > budget {
+				self.stop_with(StopReason::NodeBudget);
+				return false;
+			}
+		}
+		if let Some(limit) = self.time_limit {
+			let started = self.start_clock();
+			if nodes.is_multiple_of(DEADLINE_CHECK_INTERVAL) && started.elapsed() >= limit {
+				self.stop_with(StopReason::TimeLimit);
+				return false;
+			}
+		}
+		if self.on_progress.is_some() && nodes.is_multiple_of(self.progress_interval) {
+			self.report();
+		}
+		true
+	}
+
+	pub(crate) fn found(&self) {
+		self.results.fetch_add(1, Ordering::Relaxed);
+	}
+
+	pub(crate) fn prune(&self, reason: PruneReason) {
+		self.pruned.fetch_add(1, Ordering::Relaxed);
+		if let Some(stats) = &self.stats {
+			stats.prune(reason);
+		}
+	}
+
+	#[inline(always)]
+	pub(crate) fn count_placements(&self, tried: u64, fitted: u64) {
+		if let Some(stats) = &self.stats {
+			stats.count_placements(tried, fitted);
+		}
+	}
+
+	pub(crate) fn hit_transposition(&self) {
+		self.transposition_hits.fetch_add(1, Ordering::Relaxed);
+	}
+
+	pub(crate) fn add_jobs(&self, count: u64) {
+		if self.time_limit.is_some() {
+			self.start_clock();
+		}
+		self.jobs_total.fetch_add(count, Ordering::Relaxed);
+	}
+
+	/// When the time limit started counting, now if it hasn't yet.
+	#[inline(always)]
+	fn start_clock(&self) -> Instant {
+		*self.started.get_or_init(Instant::now)
+	}
+
+	/// When a top-level job starts, if timing them.
+	pub(crate) fn start_job(&self) -> Option<Instant> {
+		self.stats.as_ref().map(| _ | Instant::now())
+	}
+
+	pub(crate) fn finish_job(&self, started: Option<Instant>) {
+		self.jobs_done.fetch_add(1, Ordering::Relaxed);
+		if let (Some(stats), Some(started)) = (&self.stats, started) {
+			stats.time_job(started.elapsed());
+		}
+	}
+
+	/// Calls the progress callback, skipped if another thread is already in it.
+	pub(crate) fn report(&self) {
+		if let Some(on_progress) = &self.on_progress {
+			if !self.reporting.swap(true, Ordering::Acquire) {
+				on_progress(&self.get_progress());
+				self.reporting.store(false, Ordering::Release);
+			}
+		}
+	}
+
+	pub(crate) fn get_result(&self) -> Result<(), StopReason> {
+		match self.get_stop_reason() {
+			Some(reason) => Err(reason),
+			None => Ok(())
+		}
+	}
+}
@@ -0,0 +1,102 @@
+use crate::calculation::{class_at, fit_chip, is_result, is_searched, order_classes, skip_anchor, CalculationResult, CalculationResultChip, Context};
+use crate::canvas::Canvas;
+use crate::control::SearchController;
+use std::collections::BTreeMap;
+
+/// Number of layouts a search reaches.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LayoutCount {
+	pub total: u64,
+	/// Layouts by number of chips, the number being the index
+	pub by_chips: Vec<u64>,
+	pub by_correction_cost: BTreeMap<usize, u64>
+}
+
+impl LayoutCount {
+	/// Counts one more layout.
+	pub fn add(&mut self, result: &CalculationResult) {
+		self.total += 1;
+		if self.by_chips.len() <= result.len() {
+			self.by_chips.resize(result.len() + 1, 0);
+		}
+		self.by_chips[result.len()] += 1;
+		*self.by_correction_cost.entry(result.correction_cost).or_insert(0) += 1;
+	}
+}
+
+/// Counts the results below a node the way `Search` finds them, placing and taking back chips
+/// on `base` instead of making a result for every node. `false` if `controller` stopped it.
+pub(crate) fn count_below(
+	canvas: &Canvas,
+	base: &mut CalculationResult,
+	context: &Context,
+	controller: &SearchController,
+	count: &mut LayoutCount,
+	top_level: bool
+) -> bool {
+	let order = order_classes(canvas, base, context);
+	for stage in 0..=context.classes.len() {
+		let mut proceed = true;
+		match context.classes.get_classes().get(class_at(&order, stage)) {
+			Some(class) => if let Some(chip_index) = class.get_next(base) {
+				let chip = &context.all_chips[chip_index];
+				let mut fitted = 0;
+				let tried = fit_chip(canvas, chip_index, context, &mut | canvas, position, rotation | {
+					if !proceed {
+						return;
+					}
+					fitted += 1;
+					let correction_cost = base.correction_cost;
+					base.left_size -= chip.get_size();
+					base.push(CalculationResultChip {
+						chip_index,
+						position,
+						rotation
+					});
+					if chip.rotation != rotation {
+						base.correction_cost += chip.get_correction_cost();
+					}
+					proceed = count_child(&canvas, base, context, controller, count, top_level);
+					base.pop();
+					base.left_size += chip.get_size();
+					base.correction_cost = correction_cost;
+				});
+				controller.count_placements(tried, fitted);
+			},
+			None => if let Some(canvas) = skip_anchor(canvas, base, context.config) {
+				proceed = count_child(&canvas, base, context, controller, count, top_level);
+			}
+		}
+		if !proceed {
+			return false;
+		}
+	}
+	true
+}
+
+#[inline(always)]
+fn count_child(
+	canvas: &Canvas,
+	base: &mut CalculationResult,
+	context: &Context,
+	controller: &SearchController,
+	count: &mut LayoutCount,
+	top_level: bool
+) -> bool {
+	if !controller.visit(base.len()) {
+		return false;
+	}
+	let started = if top_level { controller.start_job() } else { None };
+	if is_result(canvas, base, context.config) {
+		controller.found();
+		count.add(base);
+	}
+	if is_searched(canvas, base, context, controller)
+		&& !count_below(canvas, base, context, controller, count, false) {
+		return false;
+	}
+	if top_level {
+		controller.finish_job(started);
+	}
+	true
+}
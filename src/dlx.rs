@@ -0,0 +1,379 @@
+use crate::calculation::{class_at, is_rotation_allowed, order_classes, prune, CalculationResult, CalculationResultChip, Context};
+use crate::canvas::Canvas;
+use crate::control::SearchController;
+use crate::matrix::MatrixRotation;
+use crate::rng::Rng;
+use crate::vector2::Vector2;
+
+const ROOT: usize = 0;
+
+struct Row {
+	chip_index: usize,
+	rotation: MatrixRotation,
+	position: Vector2<u8>,
+	mask: u64,
+	size: u8
+}
+
+/// Knuth's Algorithm X on dancing links.
+///
+/// There is a column for every empty cell and for every unused chip. Chip columns are secondary:
+/// they are never chosen, covering them only keeps a chip from being placed twice.
+/// Cell columns are primary while the result can't leave another cell empty; otherwise the chosen
+/// cell may also be covered on its own, leaving it empty, which makes it a secondary column too.
+pub(crate) struct Dlx {
+	left: Vec<usize>,
+	right: Vec<usize>,
+	up: Vec<usize>,
+	down: Vec<usize>,
+	column: Vec<usize>,
+	row: Vec<usize>,
+	size: Vec<usize>,
+	rows: Vec<Row>,
+	/// Cell of each cell column, in the layout of `Canvas::map`
+	cells: Vec<u64>,
+	/// Placements checked while making the rows
+	tried: u64
+}
+
+impl Dlx {
+	pub(crate) fn new(canvas: &Canvas, base: &CalculationResult, context: &Context) -> Self {
+		let mut cells = Vec::new();
+		let mut cell_column = [0usize; 64];
+		let mut empty = !canvas.map;
+		while empty != 0 {
+			let cell = empty.leading_zeros() as usize;
+			cells.push(cell);
+			cell_column[cell] = cells.len();
+			empty &= !(1u64 << (63 - cell));
+		}
+		let chip_column = cells.len() + 1;
+		let columns = cells.len() + context.all_chips.len();
+
+		let mut dlx = Self {
+			left: Vec::new(),
+			right: Vec::new(),
+			up: Vec::new(),
+			down: Vec::new(),
+			column: Vec::new(),
+			row: Vec::new(),
+			size: vec![0; columns + 1],
+			rows: Vec::new(),
+			cells: [0].iter().copied().chain(cells.iter().map(| x | 1u64 << (63 - x))).collect(),
+			tried: 0
+		};
+		for i in 0..=columns {
+			dlx.left.push(if i <= cells.len() { (i + cells.len()) % (cells.len() + 1) } else { i });
+			dlx.right.push(if i <= cells.len() { (i + 1) % (cells.len() + 1) } else { i });
+			dlx.up.push(i);
+			dlx.down.push(i);
+			dlx.column.push(i);
+			dlx.row.push(usize::MAX);
+		}
+
+		// rows of each column in the order of the classes, which the search tries them in
+		let order = order_classes(canvas, base, context);
+		let classes = context.classes.get_classes();
+		let chips = (0..classes.len()).flat_map(| x | classes[class_at(&order, x)].members.iter().copied());
+		for chip_index in chips {
+			if base.is_used(chip_index) {
+				continue;
+			}
+			let chip = &context.all_chips[chip_index];
+			for rotation in MatrixRotation::ALL.iter() {
+				if !is_rotation_allowed(chip, *rotation, context.config) {
+					continue;
+				}
+				for placement in context.index.get_placements(chip, rotation) {
+					dlx.tried += 1;
+					if canvas.put_mask(placement.mask).is_none() {
+						continue;
+					}
+					let mut columns = Vec::with_capacity(7);
+					let mut mask = placement.mask;
+					while mask != 0 {
+						let cell = mask.leading_zeros() as usize;
+						columns.push(cell_column[cell]);
+						mask &= !(1u64 << (63 - cell));
+					}
+					columns.push(chip_column + chip_index);
+					dlx.add_row(&columns, Row {
+						chip_index,
+						rotation: *rotation,
+						position: placement.position,
+						mask: placement.mask,
+						size: chip.get_size()
+					});
+				}
+			}
+		}
+		dlx
+	}
+
+	pub(crate) fn get_tried(&self) -> u64 {
+		self.tried
+	}
+
+	pub(crate) fn get_row_count(&self) -> u64 {
+		self.rows.len() as u64
+	}
+
+	fn add_row(&mut self, columns: &[usize], row: Row) {
+		let row_index = self.rows.len();
+		self.rows.push(row);
+		let first = self.left.len();
+		for (i, column) in columns.iter().enumerate() {
+			let node = first + i;
+			self.left.push(if i == 0 { first + columns.len() - 1 } else { node - 1 });
+			self.right.push(if i + 1 == columns.len() { first } else { node + 1 });
+			self.up.push(self.up[*column]);
+			self.down.push(*column);
+			self.column.push(*column);
+			self.row.push(row_index);
+			let last = self.up[*column];
+			self.down[last] = node;
+			self.up[*column] = node;
+			self.size[*column] += 1;
+		}
+	}
+
+	fn cover(&mut self, column: usize) {
+		let (left, right) = (self.left[column], self.right[column]);
+		self.right[left] = right;
+		self.left[right] = left;
+		let mut i = self.down[column];
+		while i != column {
+			let mut j = self.right[i];
+			while j != i {
+				let (up, down) = (self.up[j], self.down[j]);
+				self.down[up] = down;
+				self.up[down] = up;
+				self.size[self.column[j]] -= 1;
+				j = self.right[j];
+			}
+			i = self.down[i];
+		}
+	}
+
+	fn uncover(&mut self, column: usize) {
+		let mut i = self.up[column];
+		while i != column {
+			let mut j = self.left[i];
+			while j != i {
+				self.size[self.column[j]] += 1;
+				let (up, down) = (self.up[j], self.down[j]);
+				self.down[up] = j;
+				self.up[down] = j;
+				j = self.left[j];
+			}
+			i = self.up[i];
+		}
+		let (left, right) = (self.left[column], self.right[column]);
+		self.right[left] = column;
+		self.left[right] = column;
+	}
+
+	/// Cell column with the fewest rows left, the first one in row-major order on ties.
+	fn choose_column(&self) -> Option<usize> {
+		let mut best = None;
+		let mut c = self.right[ROOT];
+		while c != ROOT {
+			if best.is_none_or(| best | self.size[c] < self.size[best]) {
+				best = Some(c);
+			}
+			c = self.right[c];
+		}
+		best
+	}
+
+	/// Covers the other columns of row node `r` and puts its chip on `base`, returns the canvas with it.
+	fn select(&mut self, r: usize, canvas: &Canvas, base: &mut CalculationResult, context: &Context) -> Canvas {
+		let mut j = self.right[r];
+		while j != r {
+			self.cover(self.column[j]);
+			j = self.right[j];
+		}
+		let row = &self.rows[self.row[r]];
+		let chip = &context.all_chips[row.chip_index];
+		base.left_size -= row.size;
+		if chip.rotation != row.rotation {
+			base.correction_cost += chip.get_correction_cost();
+		}
+		base.push(CalculationResultChip {
+			chip_index: row.chip_index,
+			position: row.position,
+			rotation: row.rotation
+		});
+		Canvas {
+			size: canvas.size,
+			map: canvas.map | row.mask
+		}
+	}
+
+	/// Undoes `select`.
+	fn deselect(&mut self, r: usize, base: &mut CalculationResult, context: &Context) {
+		let row = &self.rows[self.row[r]];
+		let chip = &context.all_chips[row.chip_index];
+		base.pop();
+		base.left_size += row.size;
+		if chip.rotation != row.rotation {
+			base.correction_cost -= chip.get_correction_cost();
+		}
+		let mut j = self.left[r];
+		while j != r {
+			self.uncover(self.column[j]);
+			j = self.left[j];
+		}
+	}
+
+	/// Whether the search tries row node `r`: identical chips only go in order, like `ChipClass::get_next`.
+	#[inline(always)]
+	fn is_tried(&self, r: usize, base: &CalculationResult, context: &Context) -> bool {
+		!context.config.group_identical || context.classes.is_next(self.rows[self.row[r]].chip_index, base)
+	}
+
+	/// One of Knuth's probes, see `estimate`: takes a random child from each node down from this one,
+	/// returns the nodes and results there would be if every node at a depth had as many children.
+	pub(crate) fn probe(&mut self, canvas: &Canvas, base: &mut CalculationResult, context: &Context, controller: &SearchController, rng: &mut Rng) -> (f64, f64) {
+		let config = context.config;
+		let mut canvas = *canvas;
+		let (mut nodes, mut results, mut width) = (0.0, 0.0, 1.0);
+		// columns covered on the way down with the row taken, none for the cell left empty
+		let mut path: Vec<(usize, Option<usize>)> = Vec::new();
+		let mut rows = Vec::new();
+		loop {
+			let column = match self.choose_column() {
+				Some(column) => column,
+				None => {
+					results += width;
+					break;
+				}
+			};
+			let holes = base.left_size - canvas.get_left_space();
+			let can_skip = holes < config.max_empty_cells && config.forbidden_empty & self.cells[column] == 0;
+			rows.clear();
+			let mut r = self.down[column];
+			while r != column {
+				if self.is_tried(r, base, context) {
+					rows.push(r);
+				}
+				r = self.down[r];
+			}
+			let children = rows.len() + can_skip as usize;
+			if children == 0 {
+				break;
+			}
+			width *= children as f64;
+			nodes += width;
+			self.cover(column);
+			let row = rows.get(rng.below(children)).copied();
+			canvas = match row {
+				Some(r) => self.select(r, &canvas, base, context),
+				None => {
+					base.uncovered |= self.cells[column];
+					Canvas {
+						size: canvas.size,
+						map: canvas.map | self.cells[column]
+					}
+				}
+			};
+			path.push((column, row));
+			if prune(&canvas, base, context, controller) {
+				break;
+			}
+		}
+		while let Some((column, row)) = path.pop() {
+			match row {
+				Some(r) => self.deselect(r, base, context),
+				None => base.uncovered &= !self.cells[column]
+			}
+			self.uncover(column);
+		}
+		(nodes, results)
+	}
+
+	/// Calls `on_found` with the canvas and chips of every result, returns `false` if `controller`
+	/// stopped the search. With `count_jobs`, every branch of the first column counts as a top-level job.
+	pub(crate) fn search<F: FnMut(&Canvas, &CalculationResult)>(
+		&mut self,
+		canvas: &Canvas,
+		base: &mut CalculationResult,
+		context: &Context,
+		controller: &SearchController,
+		on_found: &mut F,
+		count_jobs: bool
+	) -> bool {
+		let config = context.config;
+		let column = match self.choose_column() {
+			Some(column) => column,
+			// every cell is covered or left empty
+			None => {
+				controller.found();
+				on_found(canvas, base);
+				return true;
+			}
+		};
+		let holes = base.left_size - canvas.get_left_space();
+		let can_skip = holes < config.max_empty_cells && config.forbidden_empty & self.cells[column] == 0;
+		if count_jobs {
+			controller.add_jobs((self.size[column] + can_skip as usize) as u64);
+		}
+
+		self.cover(column);
+		let mut r = self.down[column];
+		while r != column {
+			if !self.is_tried(r, base, context) {
+				if count_jobs {
+					controller.finish_job(None);
+				}
+				r = self.down[r];
+				continue;
+			}
+			let started = if count_jobs { controller.start_job() } else { None };
+			if !controller.visit(base.len() + 1) {
+				self.uncover(column);
+				return false;
+			}
+			let canvas = self.select(r, canvas, base, context);
+			let proceed = if prune(&canvas, base, context, controller) {
+				true
+			} else {
+				self.search(&canvas, base, context, controller, on_found, false)
+			};
+			self.deselect(r, base, context);
+			if !proceed {
+				self.uncover(column);
+				return false;
+			}
+			if count_jobs {
+				controller.finish_job(started);
+			}
+			r = self.down[r];
+		}
+
+		let started = if count_jobs { controller.start_job() } else { None };
+		let proceed = if can_skip {
+			let canvas = Canvas {
+				size: canvas.size,
+				map: canvas.map | self.cells[column]
+			};
+			if !controller.visit(base.len()) {
+				false
+			} else if prune(&canvas, base, context, controller) {
+				true
+			} else {
+				base.uncovered |= self.cells[column];
+				let proceed = self.search(&canvas, base, context, controller, on_found, false);
+				base.uncovered &= !self.cells[column];
+				proceed
+			}
+		} else {
+			true
+		};
+		if proceed && can_skip && count_jobs {
+			controller.finish_job(started);
+		}
+		self.uncover(column);
+		proceed
+	}
+}
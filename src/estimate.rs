@@ -0,0 +1,110 @@
+use crate::calculation::{is_result, prune, Backend, CalculationJob, CalculationResult, Context};
+use crate::canvas::Canvas;
+use crate::control::SearchController;
+use crate::dlx::Dlx;
+use crate::rng::Rng;
+use crate::search::push_children;
+use std::time::{Duration, Instant};
+
+/// Nodes searched to measure the time per node
+const CALIBRATION_NODES: u64 = 10000;
+
+/// Size of a job's search, from `CalculationJob::estimate`.
+#[derive(Clone, Copy, Debug)]
+pub struct Estimate {
+	/// Nodes the search visits, as counted by `Progress::nodes`
+	pub nodes: f64,
+	pub results: f64,
+	/// Time to visit `nodes` at the speed measured
+	pub time: Duration,
+	/// The search was small enough to run to the end while measuring its speed,
+	/// so the numbers are exact
+	pub exact: bool
+}
+
+/// Knuth's estimator: each probe walks down the tree taking a random child at every node,
+/// and counts the nodes it would have at each depth if every node there had as many children.
+/// The mean over the probes has the size of the tree as its expected value.
+/// The tree is the one `Config::backend` searches.
+pub(crate) fn estimate(job: &CalculationJob, probes: u64, seed: u64) -> Estimate {
+	let controller = SearchController::new().with_node_budget(CALIBRATION_NODES);
+	let started = Instant::now();
+	let finished = job.calculate_with(&controller, &mut | _ | ()).is_ok();
+	let elapsed = started.elapsed();
+	let progress = controller.get_progress();
+	if finished {
+		return Estimate {
+			nodes: progress.nodes as f64,
+			results: progress.results as f64,
+			time: elapsed,
+			exact: true
+		};
+	}
+
+	let context = job.get_context();
+	// only counts what it prunes
+	let controller = SearchController::new();
+	let mut rng = Rng::new(seed);
+	let (mut nodes, mut results) = (0.0, 0.0);
+	let probes = probes.max(1);
+	match context.config.backend {
+		Backend::Backtrack => {
+			let mut children = Vec::new();
+			for _ in 0..probes {
+				let (n, r) = probe(job, &context, &controller, &mut rng, &mut children);
+				nodes += n;
+				results += r;
+			}
+		},
+		Backend::DancingLinks => {
+			let mut base = job.get_base().clone();
+			let mut dlx = Dlx::new(job.get_canvas(), &base, &context);
+			for _ in 0..probes {
+				let (n, r) = dlx.probe(job.get_canvas(), &mut base, &context, &controller, &mut rng);
+				nodes += n;
+				results += r;
+			}
+		}
+	}
+	let nodes = nodes / probes as f64;
+	Estimate {
+		nodes,
+		results: results / probes as f64,
+		time: elapsed.mul_f64(nodes / progress.nodes.max(1) as f64),
+		exact: false
+	}
+}
+
+/// One probe down the backtracking search, returns its nodes and results.
+fn probe(
+	job: &CalculationJob,
+	context: &Context,
+	controller: &SearchController,
+	rng: &mut Rng,
+	children: &mut Vec<(Canvas, CalculationResult)>
+) -> (f64, f64) {
+	let mut canvas = *job.get_canvas();
+	let mut base = job.get_base().clone();
+	let (mut nodes, mut results, mut width) = (0.0, 0.0, 1.0);
+	loop {
+		children.clear();
+		for class in 0..=context.classes.len() {
+			push_children(&canvas, &base, class, context, children);
+		}
+		if children.is_empty() {
+			break;
+		}
+		width *= children.len() as f64;
+		nodes += width;
+		let (child_canvas, child_base) = children.swap_remove(rng.below(children.len()));
+		if is_result(&child_canvas, &child_base, context.config) {
+			results += width;
+		}
+		if child_canvas.get_left_space() == 0 || prune(&child_canvas, &child_base, context, controller) {
+			break;
+		}
+		canvas = child_canvas;
+		base = child_base;
+	}
+	(nodes, results)
+}
@@ -7,3 +7,20 @@ pub mod num_queue;
 pub mod chip;
 pub mod shape;
 pub mod stat;
+pub mod score;
+pub mod parallel;
+pub mod control;
+pub mod placement;
+pub mod dlx;
+pub mod tiling;
+pub mod chip_class;
+pub mod bound;
+pub mod transposition;
+pub mod search;
+pub mod stats;
+pub mod beam;
+pub mod anneal;
+pub mod anytime;
+pub mod count;
+pub mod rng;
+pub mod estimate;
@@ -1,6 +1,7 @@
 use bit_reverse::LookupReverse;
 use std::mem::replace;
 use num_derive::FromPrimitive;
+use crate::vector2::Vector2;
 
 #[derive(Clone, PartialEq, Eq)]
 pub struct Matrix {
@@ -26,6 +27,63 @@ impl Matrix {
 			self.raw_map[i] <<= rhs;
 		}
 	}
+
+	pub fn to_mask(&self) -> MatrixMask {
+		let mut mask = 0u64;
+		for i in 0..self.raw_map.len() {
+			mask |= (self.raw_map[i] as u64) << (56 - 8 * i);
+		}
+		MatrixMask {
+			mask,
+			x_size: self.x_size,
+			y_size: self.raw_map.len() as u8
+		}
+	}
+}
+
+/// `Matrix` at the top left of a `Canvas` map
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MatrixMask {
+	pub mask: u64,
+	pub x_size: u8,
+	pub y_size: u8
+}
+
+impl MatrixMask {
+	/// The mask moved to `pos`, `None` if it would leave the 8x8 board.
+	#[inline(always)]
+	pub fn at(&self, pos: &Vector2<u8>) -> Option<u64> {
+		if self.x_size + pos.x > 8 || self.y_size + pos.y > 8 {
+			return None;
+		}
+		Some(self.mask >> (pos.y * 8 + pos.x))
+	}
+
+	/// Column of the first cell of the top row.
+	pub fn get_first_x(&self) -> u8 {
+		self.mask.leading_zeros() as u8
+	}
+
+	/// Every position on the 8x8 board, column by column.
+	pub fn get_placements(&self) -> Vec<Placement> {
+		let mut placements = Vec::new();
+		for x in 0..=(8 - self.x_size) {
+			for y in 0..=(8 - self.y_size) {
+				let position = Vector2::new(x, y);
+				placements.push(Placement {
+					mask: self.at(&position).unwrap(),
+					position
+				});
+			}
+		}
+		placements
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Placement {
+	pub position: Vector2<u8>,
+	pub mask: u64
 }
 
 pub struct Rotation<'a> {
@@ -129,6 +187,8 @@ pub enum MatrixRotation {
 }
 
 impl MatrixRotation {
+	pub const ALL: [MatrixRotation; 4] = [MatrixRotation::Cw0, MatrixRotation::Cw90, MatrixRotation::Cw180, MatrixRotation::Cw270];
+
 	pub fn rotate_cw90(&mut self) {
 		let a = *self;
 		replace(self, match a {
@@ -1,31 +1,87 @@
 
-#[derive(Clone)]
+/// Set of small numbers, one bit each. The first 64 are stored inline,
+/// so cloning it doesn't allocate until a larger number is pushed.
+#[derive(Clone, Default, Debug)]
 pub struct NumQueue {
-	queue: Vec<bool>
+	first: u64,
+	rest: Vec<u64>
 }
 
 impl NumQueue {
 
+	/// Queue holding every number below `size`.
 	pub fn new(size: usize) -> Self {
-		Self {
-			queue: vec![true; size]
+		let mut queue = Self::default();
+		for v in 0..size {
+			queue.push(v);
 		}
+		queue
 	}
 
-	pub fn peek(&self) -> Option<usize> {
-		for i in 0..self.queue.len() {
-			if self.queue[i] {
-				return Some(i);
-			}
+	#[inline(always)]
+	fn word(&self, i: usize) -> u64 {
+		if i == 0 {
+			self.first
+		} else {
+			self.rest.get(i - 1).copied().unwrap_or(0)
+		}
+	}
+
+	#[inline(always)]
+	fn word_mut(&mut self, i: usize) -> &mut u64 {
+		if i == 0 {
+			return &mut self.first;
+		}
+		if self.rest.len() < i {
+			self.rest.resize(i, 0);
 		}
-		None
+		&mut self.rest[i - 1]
+	}
+
+	/// Smallest number in the queue.
+	pub fn peek(&self) -> Option<usize> {
+		(0..=self.rest.len())
+			.find(| i | self.word(*i) != 0)
+			.map(| i | i * 64 + self.word(i).trailing_zeros() as usize)
+	}
+
+	#[inline(always)]
+	pub fn contains(&self, v: usize) -> bool {
+		self.word(v / 64) & (1 << (v % 64)) != 0
 	}
 
+	#[inline(always)]
 	pub fn rm(&mut self, v: usize) {
-		self.queue[v] = false
+		if v / 64 <= self.rest.len() {
+			*self.word_mut(v / 64) &= !(1 << (v % 64));
+		}
 	}
 
+	#[inline(always)]
 	pub fn push(&mut self, v: usize) {
-		self.queue[v] = true
+		*self.word_mut(v / 64) |= 1 << (v % 64);
 	}
-}
\ No newline at end of file
+
+	pub fn len(&self) -> usize {
+		(0..=self.rest.len()).map(| i | self.word(i).count_ones() as usize).sum()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.first == 0 && self.rest.iter().all(| x | *x == 0)
+	}
+
+	/// Numbers in the queue, smallest first.
+	pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+		(0..=self.rest.len()).flat_map(move | i | {
+			let mut word = self.word(i);
+			std::iter::from_fn(move || {
+				if word == 0 {
+					return None;
+				}
+				let bit = word.trailing_zeros() as usize;
+				word &= word - 1;
+				Some(i * 64 + bit)
+			})
+		})
+	}
+}
@@ -0,0 +1,176 @@
+use crate::calculation::{is_searched, Backend, CalculationJob, CalculationResult};
+use crate::control::{SearchController, StopReason};
+use crate::score::TopK;
+use std::collections::VecDeque;
+use std::sync::{Condvar, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+#[derive(Clone, Copy, Debug)]
+pub struct ParallelConfig {
+	pub threads: usize,
+	/// Jobs shallower than this are split into their sub-jobs instead of being searched.
+	pub split_depth: u8
+}
+
+impl Default for ParallelConfig {
+	fn default() -> Self {
+		Self {
+			threads: thread::available_parallelism().map(| x | x.get()).unwrap_or(1),
+			split_depth: 2
+		}
+	}
+}
+
+/// Receives results from every worker thread.
+pub trait ResultSink: Sync {
+	fn push(&self, result: CalculationResult);
+}
+
+impl <F: Fn(CalculationResult) + Sync> ResultSink for F {
+	fn push(&self, result: CalculationResult) {
+		self(result)
+	}
+}
+
+impl ResultSink for Mutex<Vec<CalculationResult>> {
+	fn push(&self, result: CalculationResult) {
+		self.lock().unwrap().push(result)
+	}
+}
+
+impl ResultSink for Mutex<TopK<'_>> {
+	fn push(&self, result: CalculationResult) {
+		self.lock().unwrap().push(result)
+	}
+}
+
+struct Task<'a> {
+	job: CalculationJob<'a>,
+	depth: u8
+}
+
+/// One deque per worker; a worker takes its newest task and steals the oldest task of others.
+/// Workers with nothing to take sleep until a task is pushed or the search ends.
+struct Scheduler<'a, 'c> {
+	queues: Vec<Mutex<VecDeque<Task<'a>>>>,
+	pending: AtomicUsize,
+	controller: &'c SearchController,
+	idle: Mutex<()>,
+	wake: Condvar
+}
+
+impl <'a> Scheduler<'a, '_> {
+	fn push(&self, worker: usize, task: Task<'a>) {
+		self.pending.fetch_add(1, Ordering::SeqCst);
+		self.controller.add_jobs(1);
+		self.queues[worker].lock().unwrap().push_back(task);
+		let _idle = self.idle.lock().unwrap();
+		self.wake.notify_one();
+	}
+
+	/// Waits until there may be a task to take, `false` once the search is over.
+	fn wait(&self) -> bool {
+		// checked under `idle`, which is held while notifying, so no wake-up is missed
+		let mut idle = self.idle.lock().unwrap();
+		loop {
+			if self.controller.is_stopped() || self.pending.load(Ordering::SeqCst) == 0 {
+				return false;
+			}
+			if self.queues.iter().any(| x | !x.lock().unwrap().is_empty()) {
+				return true;
+			}
+			idle = self.wake.wait(idle).unwrap();
+		}
+	}
+
+	fn pop(&self, worker: usize) -> Option<Task<'a>> {
+		if let Some(task) = self.queues[worker].lock().unwrap().pop_back() {
+			return Some(task);
+		}
+		for i in 1..self.queues.len() {
+			let victim = (worker + i) % self.queues.len();
+			if let Some(task) = self.queues[victim].lock().unwrap().pop_front() {
+				return Some(task);
+			}
+		}
+		None
+	}
+
+	fn run<S: ResultSink>(&self, worker: usize, config: &ParallelConfig, sink: &S) {
+		while !self.controller.is_stopped() {
+			let task = match self.pop(worker) {
+				Some(task) => task,
+				None => {
+					if !self.wait() {
+						return;
+					}
+					continue;
+				}
+			};
+			let started = self.controller.start_job();
+			if task.depth < config.split_depth {
+				let mut jobs = task.job.generate_jobs();
+				for job in jobs.by_ref() {
+					if !self.controller.visit(job.get_base().len()) {
+						break;
+					}
+					// the same as `Search` does with each child
+					if job.is_complete() {
+						self.controller.found();
+						sink.push(job.get_base().clone().found(job.get_canvas()));
+					}
+					if is_searched(job.get_canvas(), job.get_base(), &job.get_context(), self.controller) {
+						self.push(worker, Task {
+							job,
+							depth: task.depth + 1
+						});
+					}
+				}
+				self.controller.count_placements(jobs.get_tried(), jobs.get_fitted());
+			} else {
+				task.job.search(self.controller, &mut | result | sink.push(result));
+			}
+			self.controller.finish_job(started);
+			if self.pending.fetch_sub(1, Ordering::SeqCst) == 1 || self.controller.is_stopped() {
+				let _idle = self.idle.lock().unwrap();
+				self.wake.notify_all();
+			}
+		}
+	}
+}
+
+/// Searches `job` on `config.threads` threads, pushing the same results as `CalculationJob::calculate`
+/// into `sink` in no particular order. Every task handed to a thread counts as a top-level job of `controller`.
+pub fn calculate_parallel<S: ResultSink>(
+	job: CalculationJob,
+	config: &ParallelConfig,
+	controller: &SearchController,
+	sink: &S
+) -> Result<(), StopReason> {
+	// dancing links reach every layout below a sub-job, so the sub-jobs mustn't share any
+	let job = match job.get_config().backend {
+		Backend::Backtrack => job.with_new_table(),
+		Backend::DancingLinks => job.anchored().with_new_table()
+	};
+	let threads = config.threads.max(1);
+	let scheduler = Scheduler {
+		queues: (0..threads).map(| _ | Mutex::new(VecDeque::new())).collect(),
+		pending: AtomicUsize::new(0),
+		controller,
+		idle: Mutex::new(()),
+		wake: Condvar::new()
+	};
+	scheduler.push(0, Task {
+		job,
+		depth: 0
+	});
+	thread::scope(| scope | {
+		for worker in 0..threads {
+			let scheduler = &scheduler;
+			scope.spawn(move || scheduler.run(worker, config, sink));
+		}
+	});
+	controller.report();
+	controller.get_result()
+}
@@ -0,0 +1,110 @@
+use crate::canvas::Canvas;
+use crate::matrix::{MatrixRotation, Placement};
+use crate::shape::Shape;
+use crate::vector2::Vector2;
+use enum_iterator::IntoEnumIterator;
+
+const SHAPES: usize = 39;
+const CELLS: usize = 64;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RotatedPlacement {
+	pub rotation: MatrixRotation,
+	pub placement: Placement
+}
+
+/// Every placement of every shape and rotation that fits an empty `Canvas`.
+/// Built once per board and level; the placements stay legal candidates for any canvas
+/// filled further from it.
+pub struct PlacementIndex {
+	canvas: Canvas,
+	/// By shape and rotation, column by column like `Shape::get_placements`
+	placements: Vec<[Vec<Placement>; 4]>,
+	/// By cell and shape, every placement covering the cell
+	covering: Vec<Vec<RotatedPlacement>>,
+	/// By cell and shape, every placement whose first cell in row-major order is the cell
+	anchored: Vec<Vec<RotatedPlacement>>
+}
+
+impl PlacementIndex {
+	pub fn new(canvas: &Canvas) -> Self {
+		let mut placements = Vec::with_capacity(SHAPES);
+		let mut covering = vec![Vec::new(); CELLS * SHAPES];
+		let mut anchored = vec![Vec::new(); CELLS * SHAPES];
+		for shape in Shape::into_enum_iter() {
+			let shape: Shape = shape;
+			if shape == Shape::NONE {
+				continue;
+			}
+			let mut by_rotation: [Vec<Placement>; 4] = Default::default();
+			for rotation in MatrixRotation::ALL.iter() {
+				for placement in shape.get_placements(rotation) {
+					if canvas.put_mask(placement.mask).is_none() {
+						continue;
+					}
+					by_rotation[*rotation as usize].push(*placement);
+					let rotated = RotatedPlacement {
+						rotation: *rotation,
+						placement: *placement
+					};
+					let mut mask = placement.mask;
+					anchored[Self::slot(mask.leading_zeros() as usize, &shape)].push(rotated);
+					while mask != 0 {
+						covering[Self::slot(mask.leading_zeros() as usize, &shape)].push(rotated);
+						mask &= !(1u64 << (63 - mask.leading_zeros()));
+					}
+				}
+			}
+			placements.push(by_rotation);
+		}
+		Self {
+			canvas: *canvas,
+			placements,
+			covering,
+			anchored
+		}
+	}
+
+	#[inline(always)]
+	fn slot(cell: usize, shape: &Shape) -> usize {
+		cell * SHAPES + (*shape as usize) - 1
+	}
+
+	#[inline(always)]
+	fn cell(pos: &Vector2<u8>) -> usize {
+		(pos.y * 8 + pos.x) as usize
+	}
+
+	pub fn get_canvas(&self) -> &Canvas {
+		&self.canvas
+	}
+
+	pub fn get_placements(&self, shape: &Shape, rotation: &MatrixRotation) -> &[Placement] {
+		&self.placements[(*shape as usize) - 1][*rotation as usize]
+	}
+
+	pub fn get_covering(&self, pos: &Vector2<u8>, shape: &Shape) -> &[RotatedPlacement] {
+		&self.covering[Self::slot(Self::cell(pos), shape)]
+	}
+
+	pub fn get_anchored(&self, pos: &Vector2<u8>, shape: &Shape) -> &[RotatedPlacement] {
+		&self.anchored[Self::slot(Self::cell(pos), shape)]
+	}
+
+	/// Number of distinct placements of any shape covering each cell, row-major.
+	pub fn get_coverage(&self) -> [u32; CELLS] {
+		let mut coverage = [0u32; CELLS];
+		for shape in Shape::into_enum_iter() {
+			let shape: Shape = shape;
+			if shape == Shape::NONE {
+				continue;
+			}
+			for (cell, count) in coverage.iter_mut().enumerate() {
+				*count += self.covering[Self::slot(cell, &shape)].iter()
+					.filter(| x | x.rotation as u8 <= shape.get_max_rotation())
+					.count() as u32;
+			}
+		}
+		coverage
+	}
+}
@@ -0,0 +1,28 @@
+/// xorshift64*
+pub(crate) struct Rng(u64);
+
+impl Rng {
+	pub(crate) fn new(seed: u64) -> Self {
+		// splitmix64, so that close seeds start far apart
+		let mut x = seed.wrapping_add(0x9e3779b97f4a7c15);
+		x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+		x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+		Self((x ^ (x >> 31)).max(1))
+	}
+
+	pub(crate) fn next(&mut self) -> u64 {
+		self.0 ^= self.0 >> 12;
+		self.0 ^= self.0 << 25;
+		self.0 ^= self.0 >> 27;
+		self.0.wrapping_mul(0x2545f4914f6cdd1d)
+	}
+
+	pub(crate) fn below(&mut self, n: usize) -> usize {
+		(self.next() % n as u64) as usize
+	}
+
+	/// In `[0, 1)`
+	pub(crate) fn unit(&mut self) -> f64 {
+		(self.next() >> 11) as f64 / (1u64 << 53) as f64
+	}
+}
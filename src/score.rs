@@ -0,0 +1,164 @@
+use crate::calculation::{Board, CalculationResult};
+use crate::chip::Chip;
+use crate::stat::Stat;
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+
+/// Scores a layout by how much of `cap` it fills.
+///
+/// Every point up to the cap is worth `weight`, every point past it costs
+/// `weight * overflow_penalty`, so an overflowing chip is worse than an empty cell.
+#[derive(Clone, Copy, Debug)]
+pub struct Scorer {
+	pub cap: Stat,
+	pub weight: Stat,
+	pub overflow_penalty: i32
+}
+
+impl Scorer {
+	pub fn new(cap: Stat) -> Self {
+		Self {
+			cap,
+			weight: Stat::new(1, 1, 1, 1),
+			overflow_penalty: 1
+		}
+	}
+
+	/// Scorer against the cap of `board` at `level`, `None` where `Board::get_stat_cap` is.
+	pub fn for_board(board: Board, level: u8) -> Option<Self> {
+		board.get_stat_cap(level).map(Self::new)
+	}
+
+	pub fn score(&self, stat: &Stat) -> i64 {
+		self.__score(self.weight.dmg, self.cap.dmg, stat.dmg)
+			+ self.__score(self.weight.brk, self.cap.brk, stat.brk)
+			+ self.__score(self.weight.hit, self.cap.hit, stat.hit)
+			+ self.__score(self.weight.rld, self.cap.rld, stat.rld)
+	}
+
+	fn __score(&self, weight: i32, cap: i32, value: i32) -> i64 {
+		let weight = weight as i64;
+		if value > cap {
+			weight * cap as i64 - weight * self.overflow_penalty as i64 * (value - cap) as i64
+		} else {
+			weight * value as i64
+		}
+	}
+
+	/// At least `score` of any stat up to `stat`, for the weighted points it can fill without
+	/// overflow. Assumes non-negative weights.
+	pub fn get_upper_bound(&self, stat: &Stat) -> i64 {
+		let filled = stat.min(&self.cap);
+		self.weight.dmg as i64 * filled.dmg as i64
+			+ self.weight.brk as i64 * filled.brk as i64
+			+ self.weight.hit as i64 * filled.hit as i64
+			+ self.weight.rld as i64 * filled.rld as i64
+	}
+
+	pub fn score_result(&self, result: CalculationResult, all_chips: &[Chip]) -> ScoredResult {
+		let stat = result.calculate_stat(all_chips);
+		ScoredResult {
+			score: self.score(&stat),
+			stat,
+			result
+		}
+	}
+}
+
+#[derive(Clone)]
+pub struct ScoredResult {
+	pub result: CalculationResult,
+	pub stat: Stat,
+	pub score: i64
+}
+
+impl ScoredResult {
+	fn layout_key(&self) -> impl Iterator<Item = (usize, u8, u8, u8)> + '_ {
+		self.result.iter().map(| x | (x.chip_index, x.position.y, x.position.x, x.rotation as u8))
+	}
+}
+
+/// Greater is better: higher score, then lower correction cost, then the layout itself
+/// so that ties never depend on the order results were found in.
+impl Ord for ScoredResult {
+	fn cmp(&self, other: &Self) -> Ordering {
+		self.score.cmp(&other.score)
+			.then_with(|| other.result.correction_cost.cmp(&self.result.correction_cost))
+			.then_with(|| other.layout_key().cmp(self.layout_key()))
+	}
+}
+
+impl PartialOrd for ScoredResult {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl PartialEq for ScoredResult {
+	fn eq(&self, other: &Self) -> bool {
+		self.cmp(other) == Ordering::Equal
+	}
+}
+
+impl Eq for ScoredResult {}
+
+/// Keeps the best `capacity` results pushed into it.
+pub struct TopK<'a> {
+	capacity: usize,
+	scorer: Scorer,
+	all_chips: &'a [Chip],
+	heap: BinaryHeap<Reverse<ScoredResult>>
+}
+
+impl <'a> TopK<'a> {
+	pub fn new(capacity: usize, scorer: Scorer, all_chips: &'a [Chip]) -> Self {
+		Self {
+			capacity,
+			scorer,
+			all_chips,
+			heap: BinaryHeap::with_capacity(capacity + 1)
+		}
+	}
+
+	pub fn push(&mut self, result: CalculationResult) {
+		if self.capacity == 0 {
+			return;
+		}
+		self.push_scored(self.scorer.score_result(result, self.all_chips));
+	}
+
+	/// Pushes a result already scored by the same scorer.
+	pub fn push_scored(&mut self, scored: ScoredResult) {
+		if self.capacity == 0 {
+			return;
+		}
+		if self.heap.len() < self.capacity {
+			self.heap.push(Reverse(scored));
+		} else if let Some(mut worst) = self.heap.peek_mut() {
+			if scored > worst.0 {
+				*worst = Reverse(scored);
+			}
+		}
+	}
+
+	/// The worst result kept, once the collector is full.
+	pub fn threshold(&self) -> Option<&ScoredResult> {
+		if self.heap.len() < self.capacity {
+			return None;
+		}
+		self.heap.peek().map(| x | &x.0)
+	}
+
+	pub fn len(&self) -> usize {
+		self.heap.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.heap.is_empty()
+	}
+
+	/// Best result first.
+	pub fn into_sorted_vec(self) -> Vec<ScoredResult> {
+		self.heap.into_sorted_vec().into_iter().map(| x | x.0).collect()
+	}
+}
@@ -0,0 +1,431 @@
+use crate::calculation::{class_at, is_result, is_rotation_allowed, is_searched, order_classes, put_chip, skip_anchor, Backend, CalculationJob, CalculationResult, CalculationResultChip, Config, Context};
+use crate::canvas::Canvas;
+use crate::control::{SearchController, StopReason};
+use crate::matrix::MatrixRotation;
+use crate::vector2::Vector2;
+use num_traits::FromPrimitive;
+use serde_json::{json, Value};
+use std::time::Instant;
+
+const CHECKPOINT_VERSION: u64 = 3;
+
+/// Stage of the root frame, which makes all of its children at once
+const ROOT: usize = usize::MAX;
+
+#[derive(PartialEq, Eq)]
+enum Pause {
+	Done,
+	Stopped,
+	/// `on_found` asked to
+	Paused
+}
+
+/// A node being searched: children of one stage are made at a time, the chips of one class,
+/// then leaving the anchor empty.
+struct Frame {
+	canvas: Canvas,
+	base: CalculationResult,
+	/// Classes in the order of the stages, see `order_classes`
+	order: Vec<usize>,
+	/// Stages whose children were made, the last one's are in `children`
+	stage: usize,
+	/// Children not taken yet, the next one last
+	children: Vec<(Canvas, CalculationResult)>,
+	/// Children already taken
+	child: usize
+}
+
+impl Frame {
+	fn new(
+		canvas: Canvas,
+		base: CalculationResult,
+		order: Vec<usize>,
+		stage: usize,
+		mut children: Vec<(Canvas, CalculationResult)>,
+		child: usize
+	) -> Option<Self> {
+		if child > children.len() {
+			return None;
+		}
+		children.reverse();
+		children.truncate(children.len() - child);
+		Some(Self {
+			canvas,
+			base,
+			order,
+			stage,
+			children,
+			child
+		})
+	}
+}
+
+/// Backtracking search of a `CalculationJob` on an explicit stack, so it can stop at any node
+/// and go on later, from the same value or from a checkpoint in another process.
+///
+/// Always backtracks, following `SearchMode`; jobs with `Backend::DancingLinks` in `SearchMode::AnchorCell`,
+/// which reaches the same layouts as `CalculationJob::calculate` does with them.
+/// The children of the job itself are its top-level jobs, in the order of `CalculationJob::generate_jobs`.
+pub struct Search<'a> {
+	job: CalculationJob<'a>,
+	stack: Vec<Frame>,
+	/// Emptied `children` of finished frames, kept to save allocations
+	spare: Vec<Vec<(Canvas, CalculationResult)>>,
+	/// When the top-level job being searched started, if the controller times them
+	job_started: Option<Instant>,
+	/// Placements tried and fitted making the top-level jobs, counted by the next run
+	root_placements: (u64, u64)
+}
+
+impl <'a> Search<'a> {
+	/// Search of `job` from the start, with an empty transposition table.
+	pub fn new(job: CalculationJob<'a>) -> Self {
+		Self::sharing(Self::backtracked(&job))
+	}
+
+	/// The job to backtrack for `job`, see `Search`.
+	fn backtracked(job: &CalculationJob<'a>) -> CalculationJob<'a> {
+		match job.get_config().backend {
+			Backend::Backtrack => job.with_new_table(),
+			Backend::DancingLinks => job.anchored().with_new_table()
+		}
+	}
+
+	/// Search of `job` keeping the transposition table it shares with the other parts of a search.
+	pub(crate) fn sharing(job: CalculationJob<'a>) -> Self {
+		let (root, root_placements) = Self::root(&job, 0).unwrap();
+		Self {
+			job,
+			stack: vec![root],
+			spare: Vec::new(),
+			job_started: None,
+			root_placements
+		}
+	}
+
+	/// The root frame, with the placements tried and fitted making its children.
+	fn root(job: &CalculationJob, child: usize) -> Option<(Frame, (u64, u64))> {
+		let mut jobs = job.clone().generate_jobs();
+		let children = jobs.by_ref().map(| x | (*x.get_canvas(), x.get_base().clone())).collect();
+		let frame = Frame::new(*job.get_canvas(), job.get_base().clone(), Vec::new(), ROOT, children, child)?;
+		Some((frame, (jobs.get_tried(), jobs.get_fitted())))
+	}
+
+	pub fn get_job(&self) -> &CalculationJob<'a> {
+		&self.job
+	}
+
+	pub fn is_done(&self) -> bool {
+		self.stack.is_empty()
+	}
+
+	/// Top-level jobs not finished yet.
+	pub fn get_jobs_left(&self) -> u64 {
+		match self.stack.first() {
+			Some(root) => (root.children.len() + (self.stack.len() > 1) as usize) as u64,
+			None => 0
+		}
+	}
+
+	/// Searches until done, or until `controller` stops it. Results found before stopping are
+	/// never found again, so `run` can be called again with another controller to go on.
+	pub fn run<F: FnMut(CalculationResult)>(&mut self, controller: &SearchController, on_found: &mut F) -> Result<(), StopReason> {
+		controller.add_jobs(self.get_jobs_left());
+		self.__run(controller, on_found, true);
+		controller.report();
+		controller.get_result()
+	}
+
+	/// Returns `false` if `controller` stopped it.
+	pub(crate) fn __run<F: FnMut(CalculationResult)>(&mut self, controller: &SearchController, on_found: &mut F, count_jobs: bool) -> bool {
+		self.__run_until(controller, &mut | result | {
+			on_found(result);
+			true
+		}, count_jobs) != Pause::Stopped
+	}
+
+	/// Searches until done, until `controller` stops it, or until `on_found` returns `false`.
+	fn __run_until<F: FnMut(CalculationResult) -> bool>(&mut self, controller: &SearchController, on_found: &mut F, count_jobs: bool) -> Pause {
+		let context = self.job.get_context();
+		let (tried, fitted) = std::mem::take(&mut self.root_placements);
+		controller.count_placements(tried, fitted);
+		while let Some(frame) = self.stack.last_mut() {
+			if let Some((_, base)) = frame.children.last() {
+				if !controller.visit(base.len()) {
+					return Pause::Stopped;
+				}
+				let (canvas, base) = frame.children.pop().unwrap();
+				frame.child += 1;
+				let top_level = self.stack.len() == 1;
+				if top_level && count_jobs {
+					self.job_started = controller.start_job();
+				}
+				let found = is_result(&canvas, &base, context.config);
+				// results in `SearchMode::FirstFit` may still have room for more chips
+				let searched = is_searched(&canvas, &base, &context, controller);
+				let mut result = None;
+				if searched {
+					if found {
+						result = Some(base.clone().found(&canvas));
+					}
+					self.stack.push(Frame {
+						canvas,
+						base,
+						order: Vec::new(),
+						stage: 0,
+						children: self.spare.pop().unwrap_or_default(),
+						child: 0
+					});
+				} else if found {
+					result = Some(base.found(&canvas));
+				}
+				let proceed = match result {
+					Some(result) => {
+						controller.found();
+						on_found(result)
+					},
+					None => true
+				};
+				if !searched && top_level && count_jobs {
+					controller.finish_job(self.job_started.take());
+				}
+				if !proceed {
+					return Pause::Paused;
+				}
+			} else if frame.stage <= context.classes.len() {
+				if frame.stage == 0 {
+					frame.order = order_classes(&frame.canvas, &frame.base, &context);
+				}
+				let mut tried = 0;
+				while frame.children.is_empty() && frame.stage <= context.classes.len() {
+					frame.stage += 1;
+					tried += push_children(&frame.canvas, &frame.base, class_at(&frame.order, frame.stage - 1), &context, &mut frame.children);
+				}
+				// the last stage leaves the anchor empty instead of placing a chip
+				let fitted = if frame.stage > context.classes.len() { 0 } else { frame.children.len() as u64 };
+				controller.count_placements(tried, fitted);
+				frame.children.reverse();
+				frame.child = 0;
+			} else {
+				if let Some(frame) = self.stack.pop() {
+					self.spare.push(frame.children);
+				}
+				if self.stack.len() == 1 && count_jobs {
+					controller.finish_job(self.job_started.take());
+				}
+			}
+		}
+		Pause::Done
+	}
+
+	/// Results from here on one at a time, searching only as far as the next one.
+	pub fn into_results(self, controller: SearchController) -> Results<'a> {
+		controller.add_jobs(self.get_jobs_left());
+		Results {
+			search: self,
+			controller
+		}
+	}
+
+	/// Where the search is, as JSON, along with the transposition table if there is one.
+	/// `resume` takes it back with the same job.
+	pub fn checkpoint(&self) -> Value {
+		let context = self.job.get_context();
+		json!({
+			"version": CHECKPOINT_VERSION,
+			"chips": context.all_chips.len(),
+			"config": config_json(context.config),
+			"transpositions": context.transpositions.map(| x | x.get_entries()),
+			"stack": self.stack.iter().map(| frame | json!({
+				"map": frame.canvas.map,
+				"chips": frame.base.iter().map(| x | json!([x.chip_index, x.position.x, x.position.y, x.rotation as u8])).collect::<Vec<Value>>(),
+				"correction_cost": frame.base.correction_cost,
+				"left_size": frame.base.left_size,
+				"uncovered": frame.base.uncovered,
+				"stage": if frame.stage == ROOT { Value::Null } else { json!(frame.stage) },
+				"child": frame.child
+			})).collect::<Vec<Value>>()
+		})
+	}
+
+	/// The search at `checkpoint`, taken from a search of an equal `job`: same canvas, chips and config.
+	/// `None` if the checkpoint is malformed or doesn't belong to `job`.
+	pub fn resume(job: CalculationJob<'a>, checkpoint: &Value) -> Option<Self> {
+		let job = Self::backtracked(&job);
+		if checkpoint["version"].as_u64()? != CHECKPOINT_VERSION
+			|| checkpoint["chips"].as_u64()? != job.get_context().all_chips.len() as u64
+			|| checkpoint["config"] != config_json(job.get_config()) {
+			return None;
+		}
+		if let Some(table) = job.get_context().transpositions {
+			let mut entries = Vec::new();
+			for entry in checkpoint["transpositions"].as_array()? {
+				entries.push((entry[0].as_u64()? as usize, entry[1].as_u64()?));
+			}
+			table.restore(&entries);
+		}
+		let mut stack = Vec::new();
+		let mut root_placements = (0, 0);
+		for (depth, value) in checkpoint["stack"].as_array()?.iter().enumerate() {
+			let canvas = Canvas {
+				size: job.get_canvas().size,
+				map: value["map"].as_u64()?
+			};
+			let mut base = CalculationResult::new(job.get_canvas());
+			for chip in value["chips"].as_array()? {
+				let chip_index = chip[0].as_u64()? as usize;
+				if chip_index >= job.get_context().all_chips.len() || base.is_used(chip_index) {
+					return None;
+				}
+				base.push(CalculationResultChip {
+					chip_index,
+					position: Vector2::new(chip[1].as_u64()? as u8, chip[2].as_u64()? as u8),
+					rotation: MatrixRotation::from_u64(chip[3].as_u64()?)?
+				});
+			}
+			base.correction_cost = value["correction_cost"].as_u64()? as usize;
+			base.left_size = value["left_size"].as_u64()? as u8;
+			base.uncovered = value["uncovered"].as_u64()?;
+			if !is_node(&job, &canvas, &base) {
+				return None;
+			}
+			let child = value["child"].as_u64()? as usize;
+			let frame = if depth == 0 {
+				if canvas != *job.get_canvas() || base.len() != job.get_base().len() {
+					return None;
+				}
+				let (frame, placements) = Self::root(&job, child)?;
+				root_placements = placements;
+				frame
+			} else {
+				let stage = value["stage"].as_u64()? as usize;
+				if stage > job.get_context().classes.len() + 1 {
+					return None;
+				}
+				let mut children = Vec::new();
+				let mut order = Vec::new();
+				if stage > 0 {
+					order = order_classes(&canvas, &base, &job.get_context());
+					push_children(&canvas, &base, class_at(&order, stage - 1), &job.get_context(), &mut children);
+				}
+				Frame::new(canvas, base, order, stage, children, child)?
+			};
+			stack.push(frame);
+		}
+		Some(Self {
+			job,
+			stack,
+			spare: Vec::new(),
+			job_started: None,
+			root_placements
+		})
+	}
+}
+
+/// What a checkpoint has to be resumed with of `config`
+fn config_json(config: &Config) -> Value {
+	json!({
+		"max_empty_cells": config.max_empty_cells,
+		"forbidden_empty": config.forbidden_empty,
+		"rotate": config.rotate,
+		"search": format!("{:?}", config.search),
+		"exhaustive": config.exhaustive,
+		"backend": format!("{:?}", config.backend),
+		"group_identical": config.group_identical,
+		"prune_dead_regions": config.prune_dead_regions,
+		"transposition_table_size": config.transposition_table_size,
+		"order": format!("{:?}", config.order)
+	})
+}
+
+/// Whether `canvas` and `base` can be a node of a search of `job`: its base followed by chips
+/// fitting one after another in rotations the config allows, with the cells left empty on purpose
+/// and the left size and correction cost to match.
+fn is_node(job: &CalculationJob, canvas: &Canvas, base: &CalculationResult) -> bool {
+	let context = job.get_context();
+	let root = job.get_base();
+	if base.len() < root.len() || root.iter().zip(base.iter()).any(| (a, b) | a.chip_index != b.chip_index || a.position != b.position || a.rotation != b.rotation) {
+		return false;
+	}
+	let mut map = job.get_canvas().map;
+	let mut left_size = root.left_size;
+	let mut correction_cost = root.correction_cost;
+	for x in &base[root.len()..] {
+		let chip = &context.all_chips[x.chip_index];
+		if !is_rotation_allowed(chip, x.rotation, context.config) {
+			return false;
+		}
+		match context.index.get_placements(chip, &x.rotation).iter().find(| p | p.position == x.position) {
+			Some(placement) if placement.mask & map == 0 => map |= placement.mask,
+			_ => return false
+		}
+		left_size -= chip.get_size();
+		if chip.rotation != x.rotation {
+			correction_cost += chip.get_correction_cost();
+		}
+	}
+	let skipped = base.uncovered & !root.uncovered;
+	base.uncovered & root.uncovered == root.uncovered
+		&& skipped & map == 0
+		&& map | skipped == canvas.map
+		&& left_size == base.left_size
+		&& correction_cost == base.correction_cost
+}
+
+/// Pushes the children of a node made by a stage: placing the next chip of `class`,
+/// or past the last class, leaving the anchor empty. Returns the number of placements tried.
+#[inline(always)]
+pub(crate) fn push_children(canvas: &Canvas, base: &CalculationResult, class: usize, context: &Context, children: &mut Vec<(Canvas, CalculationResult)>) -> u64 {
+	match context.classes.get_classes().get(class) {
+		Some(class) => match class.get_next(base) {
+			Some(chip_index) => put_chip(canvas, base, chip_index, context, &mut | canvas, base | children.push((canvas, base))),
+			None => 0
+		},
+		None => {
+			if let Some(skipped) = skip_anchor(canvas, base, context.config) {
+				children.push((skipped, base.skipped(canvas, &skipped)));
+			}
+			0
+		}
+	}
+}
+
+/// Iterator over the results of a `Search`, in the order `Search::run` finds them.
+/// The search only goes on while the next result is asked for, so dropping the iterator
+/// or taking a few results stops it without a controller. The iterator ends early if
+/// its controller stops the search.
+pub struct Results<'a> {
+	search: Search<'a>,
+	controller: SearchController
+}
+
+impl <'a> Results<'a> {
+	pub fn get_search(&self) -> &Search<'a> {
+		&self.search
+	}
+
+	pub fn get_controller(&self) -> &SearchController {
+		&self.controller
+	}
+
+	/// The search where the iterator stopped, to checkpoint or run on.
+	pub fn into_search(self) -> Search<'a> {
+		self.search
+	}
+}
+
+impl Iterator for Results<'_> {
+	type Item = CalculationResult;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let mut found = None;
+		let pause = self.search.__run_until(&self.controller, &mut | result | {
+			found = Some(result);
+			false
+		}, true);
+		if pause != Pause::Paused {
+			self.controller.report();
+		}
+		found
+	}
+}
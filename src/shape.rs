@@ -2,7 +2,7 @@
 
 use std::cmp::{Ordering, Ord};
 use num_derive::FromPrimitive;
-use crate::matrix::{Matrix, MatrixRotationCache};
+use crate::matrix::{Matrix, MatrixRotationCache, MatrixMask, MatrixRotation, Placement};
 use crate::vector2::Vector2;
 use std::collections::HashMap;
 use enum_iterator::IntoEnumIterator;
@@ -65,7 +65,7 @@ impl Type {
 
 impl PartialEq for Type {
 	fn eq(&self, other: &Self) -> bool {
-		self == other
+		(*self as u8) == (*other as u8)
 	}
 }
 
@@ -505,6 +505,15 @@ impl Shape {
 		MAX_ROTATION[(*self as usize) - 1]
 	}
 
+	pub fn get_mask(&self, rotation: &MatrixRotation) -> &MatrixMask {
+		&MASK_CACHE[(*self as usize) - 1][*rotation as usize]
+	}
+
+	/// Every position of the shape rotated by `rotation` on an 8x8 board, column by column.
+	pub fn get_placements(&self, rotation: &MatrixRotation) -> &[Placement] {
+		&PLACEMENT_CACHE[(*self as usize) - 1][*rotation as usize]
+	}
+
 }
 
 lazy_static::lazy_static! {
@@ -537,6 +546,22 @@ static ref ROTATION_CACHE: Vec<MatrixRotationCache> = {
 	map
 };
 
+static ref MASK_CACHE: Vec<[MatrixMask; 4]> = {
+	let mut map: Vec<[MatrixMask; 4]> = Vec::with_capacity(39);
+	for cache in ROTATION_CACHE.iter() {
+		map.push([cache.cw0.to_mask(), cache.cw90.to_mask(), cache.cw180.to_mask(), cache.cw270.to_mask()]);
+	}
+	map
+};
+
+static ref PLACEMENT_CACHE: Vec<[Vec<Placement>; 4]> = {
+	let mut map: Vec<[Vec<Placement>; 4]> = Vec::with_capacity(39);
+	for masks in MASK_CACHE.iter() {
+		map.push([masks[0].get_placements(), masks[1].get_placements(), masks[2].get_placements(), masks[3].get_placements()]);
+	}
+	map
+};
+
 }
 
 
@@ -1,6 +1,6 @@
 use std::ops::{Add, AddAssign};
 
-#[derive(Default, Clone)]
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Stat {
     pub dmg: i32,
     pub brk: i32,
@@ -17,6 +17,61 @@ impl Stat {
             rld
         }
     }
+
+    pub fn from_array(values: [i32; 4]) -> Self {
+        Self::new(values[0], values[1], values[2], values[3])
+    }
+
+    /// `dmg`, `brk`, `hit` and `rld` in that order.
+    pub fn to_array(&self) -> [i32; 4] {
+        [self.dmg, self.brk, self.hit, self.rld]
+    }
+
+    pub fn min(&self, other: &Stat) -> Stat {
+        Stat::new(
+            self.dmg.min(other.dmg),
+            self.brk.min(other.brk),
+            self.hit.min(other.hit),
+            self.rld.min(other.rld)
+        )
+    }
+
+    pub fn max(&self, other: &Stat) -> Stat {
+        Stat::new(
+            self.dmg.max(other.dmg),
+            self.brk.max(other.brk),
+            self.hit.max(other.hit),
+            self.rld.max(other.rld)
+        )
+    }
+
+    /// How far each stat goes past `cap`, zero where it doesn't.
+    pub fn get_overflow(&self, cap: &Stat) -> Stat {
+        Stat::new(
+            (self.dmg - cap.dmg).max(0),
+            (self.brk - cap.brk).max(0),
+            (self.hit - cap.hit).max(0),
+            (self.rld - cap.rld).max(0)
+        )
+    }
+
+    pub fn sum(&self) -> i32 {
+        self.dmg + self.brk + self.hit + self.rld
+    }
+
+    /// Share of `cap` filled without counting overflow, in percent.
+    pub fn get_completion(&self, cap: &Stat) -> f32 {
+        let total = cap.sum();
+        if total == 0 {
+            return 100.0;
+        }
+        self.min(cap).sum() as f32 * 100.0 / total as f32
+    }
+
+    /// Every stat exactly at `cap`.
+    pub fn is_perfect(&self, cap: &Stat) -> bool {
+        self == cap
+    }
 }
 
 impl Add for Stat {
@@ -0,0 +1,122 @@
+use serde_json::{json, Value};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Deepest node counted by depth; a node can't hold more chips than the board has cells
+const MAX_DEPTH: usize = 64;
+
+/// Why a node was not searched below.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PruneReason {
+	/// See `Config::prune_dead_regions`
+	DeadRegion = 0,
+	/// See `StatBound`
+	Bound = 1
+}
+
+/// Report of what a search did, from a `SearchController` made `with_stats`.
+#[derive(Clone, Debug, Default)]
+pub struct SearchStats {
+	/// Nodes visited, by number of chips placed in them
+	pub nodes_by_depth: Vec<u64>,
+	/// Placements checked against the canvas
+	pub placements_tried: u64,
+	/// Placements that fit, each making a child node
+	pub placements_fitted: u64,
+	pub results: u64,
+	pub pruned_dead_region: u64,
+	pub pruned_bound: u64,
+	pub transposition_hits: u64,
+	/// Time spent in each top-level job, in the order they finished
+	pub job_times: Vec<Duration>,
+	/// Since the controller was made
+	pub elapsed: Duration
+}
+
+impl SearchStats {
+	pub fn get_nodes(&self) -> u64 {
+		self.nodes_by_depth.iter().sum()
+	}
+
+	/// Times in milliseconds.
+	pub fn to_json(&self) -> Value {
+		let millis = | x: &Duration | x.as_secs_f64() * 1000.0;
+		json!({
+			"nodes": self.get_nodes(),
+			"nodes_by_depth": self.nodes_by_depth,
+			"placements_tried": self.placements_tried,
+			"placements_fitted": self.placements_fitted,
+			"results": self.results,
+			"pruned": {
+				"dead_region": self.pruned_dead_region,
+				"bound": self.pruned_bound
+			},
+			"transposition_hits": self.transposition_hits,
+			"job_times": self.job_times.iter().map(millis).collect::<Vec<f64>>(),
+			"elapsed": millis(&self.elapsed)
+		})
+	}
+}
+
+/// Counters behind `SearchStats`, updated from every thread of a search.
+pub(crate) struct StatsCounter {
+	started: Instant,
+	nodes_by_depth: Vec<AtomicU64>,
+	placements_tried: AtomicU64,
+	placements_fitted: AtomicU64,
+	pruned: [AtomicU64; 2],
+	job_times: Mutex<Vec<Duration>>
+}
+
+impl StatsCounter {
+	pub(crate) fn new() -> Self {
+		Self {
+			started: Instant::now(),
+			nodes_by_depth: (0..=MAX_DEPTH).map(| _ | AtomicU64::new(0)).collect(),
+			placements_tried: AtomicU64::new(0),
+			placements_fitted: AtomicU64::new(0),
+			pruned: [AtomicU64::new(0), AtomicU64::new(0)],
+			job_times: Mutex::new(Vec::new())
+		}
+	}
+
+	#[inline(always)]
+	pub(crate) fn visit(&self, depth: usize) {
+		self.nodes_by_depth[depth.min(MAX_DEPTH)].fetch_add(1, Ordering::Relaxed);
+	}
+
+	#[inline(always)]
+	pub(crate) fn prune(&self, reason: PruneReason) {
+		self.pruned[reason as usize].fetch_add(1, Ordering::Relaxed);
+	}
+
+	#[inline(always)]
+	pub(crate) fn count_placements(&self, tried: u64, fitted: u64) {
+		self.placements_tried.fetch_add(tried, Ordering::Relaxed);
+		self.placements_fitted.fetch_add(fitted, Ordering::Relaxed);
+	}
+
+	pub(crate) fn time_job(&self, time: Duration) {
+		self.job_times.lock().unwrap().push(time);
+	}
+
+	/// Report with the counters the controller keeps anyway.
+	pub(crate) fn get_stats(&self, results: u64, transposition_hits: u64) -> SearchStats {
+		let mut nodes_by_depth: Vec<u64> = self.nodes_by_depth.iter().map(| x | x.load(Ordering::Relaxed)).collect();
+		while nodes_by_depth.last() == Some(&0) {
+			nodes_by_depth.pop();
+		}
+		SearchStats {
+			nodes_by_depth,
+			placements_tried: self.placements_tried.load(Ordering::Relaxed),
+			placements_fitted: self.placements_fitted.load(Ordering::Relaxed),
+			results,
+			pruned_dead_region: self.pruned[PruneReason::DeadRegion as usize].load(Ordering::Relaxed),
+			pruned_bound: self.pruned[PruneReason::Bound as usize].load(Ordering::Relaxed),
+			transposition_hits,
+			job_times: self.job_times.lock().unwrap().clone(),
+			elapsed: self.started.elapsed()
+		}
+	}
+}
@@ -0,0 +1,330 @@
+use crate::calculation::{CalculationResult, CalculationResultChip, Config};
+use crate::canvas::Canvas;
+use crate::chip::Chip;
+use crate::matrix::MatrixRotation;
+use crate::placement::PlacementIndex;
+use crate::score::{ScoredResult, Scorer};
+use crate::shape::Shape;
+use crate::stat::Stat;
+use crate::vector2::Vector2;
+
+/// Chips that can fill the same pieces of a tiling: same shape, and same rotation unless
+/// `Config::rotate` is set.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ShapeKind {
+	pub shape: Shape,
+	pub rotation: Option<MatrixRotation>
+}
+
+impl ShapeKind {
+	pub fn of(chip: &Chip, config: &Config) -> Self {
+		Self {
+			shape: chip.shape,
+			rotation: if config.rotate { None } else { Some(chip.rotation) }
+		}
+	}
+
+	fn allows(&self, rotation: MatrixRotation) -> bool {
+		match self.rotation {
+			Some(x) => x == rotation,
+			None => rotation as u8 <= self.shape.get_max_rotation()
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TilePiece {
+	pub kind: ShapeKind,
+	pub rotation: MatrixRotation,
+	pub position: Vector2<u8>
+}
+
+/// Layout of shapes without chips assigned yet.
+#[derive(Clone, Debug)]
+pub struct ShapeTiling {
+	pub pieces: Vec<TilePiece>,
+	pub left_size: u8,
+	/// Cells no piece covers, in the layout of `Canvas::map`
+	pub uncovered: u64
+}
+
+/// Phase one: every distinct tiling of `canvas` by the shapes of the unused chips,
+/// each reached once by covering the first empty cell in row-major order.
+pub(crate) struct Tiler<'c> {
+	kinds: Vec<ShapeKind>,
+	counts: Vec<usize>,
+	config: &'c Config,
+	index: &'c PlacementIndex
+}
+
+impl <'c> Tiler<'c> {
+	pub(crate) fn new(all_chips: &[Chip], base: &CalculationResult, config: &'c Config, index: &'c PlacementIndex) -> Self {
+		let mut kinds: Vec<ShapeKind> = Vec::new();
+		let mut counts: Vec<usize> = Vec::new();
+		for (chip_index, chip) in all_chips.iter().enumerate() {
+			if base.is_used(chip_index) {
+				continue;
+			}
+			let kind = ShapeKind::of(chip, config);
+			match kinds.iter().position(| x | *x == kind) {
+				Some(i) => counts[i] += 1,
+				None => {
+					kinds.push(kind);
+					counts.push(1);
+				}
+			}
+		}
+		Self {
+			kinds,
+			counts,
+			config,
+			index
+		}
+	}
+
+	/// `uncovered` are the cells already left empty.
+	pub(crate) fn tile<F: FnMut(ShapeTiling)>(&mut self, canvas: &Canvas, left_size: u8, uncovered: u64, on_tiling: &mut F) {
+		if canvas.get_left_space() > 0 {
+			self.__tile(canvas, &mut Vec::with_capacity(8), left_size, uncovered, on_tiling)
+		}
+	}
+
+	fn __tile<F: FnMut(ShapeTiling)>(
+		&mut self,
+		canvas: &Canvas,
+		pieces: &mut Vec<TilePiece>,
+		left_size: u8,
+		uncovered: u64,
+		on_tiling: &mut F
+	) {
+		let anchor = match canvas.get_anchor() {
+			Some(anchor) => anchor,
+			// every cell is covered or left empty
+			None => return on_tiling(ShapeTiling {
+				pieces: pieces.clone(),
+				left_size,
+				uncovered
+			})
+		};
+		for k in 0..self.kinds.len() {
+			if self.counts[k] == 0 {
+				continue;
+			}
+			let kind = self.kinds[k];
+			for rotated in self.index.get_anchored(&anchor, &kind.shape) {
+				if !kind.allows(rotated.rotation) {
+					continue;
+				}
+				let canvas = match canvas.put_mask(rotated.placement.mask) {
+					Some(canvas) => canvas,
+					None => continue
+				};
+				let left_size = left_size - kind.shape.get_size();
+				pieces.push(TilePiece {
+					kind,
+					rotation: rotated.rotation,
+					position: rotated.placement.position
+				});
+				self.counts[k] -= 1;
+				self.__tile(&canvas, pieces, left_size, uncovered, on_tiling);
+				self.counts[k] += 1;
+				pieces.pop();
+			}
+		}
+		let cell = Canvas::get_bit(&anchor);
+		if (uncovered.count_ones() as u8) < self.config.max_empty_cells && self.config.forbidden_empty & cell == 0 {
+			let mut canvas = *canvas;
+			canvas.fill(&anchor);
+			self.__tile(&canvas, pieces, left_size, uncovered | cell, on_tiling);
+		}
+	}
+}
+
+/// Phase two: picks the chips for the pieces of a tiling that score best.
+pub struct Assigner<'a> {
+	all_chips: &'a [Chip],
+	base: CalculationResult,
+	base_stat: Stat,
+	scorer: Scorer,
+	/// Unused chips of each kind, best weighted stat first
+	candidates: Vec<(ShapeKind, Vec<usize>)>,
+	stats: Vec<Stat>
+}
+
+struct Assignment {
+	chips: Vec<usize>,
+	score: i64,
+	correction_cost: usize
+}
+
+impl <'a> Assigner<'a> {
+	pub fn new(all_chips: &'a [Chip], base: &CalculationResult, scorer: Scorer, config: Config) -> Self {
+		let stats: Vec<Stat> = all_chips.iter().map(| x | x.get_stat()).collect();
+		let mut candidates: Vec<(ShapeKind, Vec<usize>)> = Vec::new();
+		for (chip_index, chip) in all_chips.iter().enumerate() {
+			if base.is_used(chip_index) {
+				continue;
+			}
+			let kind = ShapeKind::of(chip, &config);
+			match candidates.iter_mut().find(| x | x.0 == kind) {
+				Some(x) => x.1.push(chip_index),
+				None => candidates.push((kind, vec![chip_index]))
+			}
+		}
+		for (_, chips) in candidates.iter_mut() {
+			chips.sort_by_key(| x | -scorer.get_upper_bound(&stats[*x]));
+		}
+		Self {
+			all_chips,
+			base_stat: base.calculate_stat(all_chips),
+			base: base.clone(),
+			scorer,
+			candidates,
+			stats
+		}
+	}
+
+	/// Best scoring chips for the pieces of `tiling`, lower correction cost first on ties.
+	/// `None` if there aren't enough chips of some kind.
+	pub fn assign(&self, tiling: &ShapeTiling) -> Option<ScoredResult> {
+		// pieces of each kind, in the order of `candidates`
+		let mut groups: Vec<(usize, Vec<&TilePiece>)> = Vec::new();
+		for piece in &tiling.pieces {
+			let k = self.candidates.iter().position(| x | x.0 == piece.kind)?;
+			match groups.iter_mut().find(| x | x.0 == k) {
+				Some(x) => x.1.push(piece),
+				None => groups.push((k, vec![piece]))
+			}
+		}
+		for (k, pieces) in &groups {
+			if self.candidates[*k].1.len() < pieces.len() {
+				return None;
+			}
+		}
+
+		let mut best: Option<Assignment> = None;
+		let mut chips = Vec::with_capacity(tiling.pieces.len());
+		self.__assign(&groups, 0, 0, self.base_stat, &mut chips, &mut best);
+		let best = best?;
+
+		let mut result = self.base.clone();
+		result.uncovered = tiling.uncovered;
+		let mut offset = 0;
+		for (_, pieces) in &groups {
+			let chips = &best.chips[offset..offset + pieces.len()];
+			offset += pieces.len();
+			for (piece, chip_index) in pieces.iter().zip(self.match_rotations(pieces, chips)) {
+				let chip = &self.all_chips[chip_index];
+				result.left_size -= chip.get_size();
+				if chip.rotation != piece.rotation {
+					result.correction_cost += chip.get_correction_cost();
+				}
+				result.push(CalculationResultChip {
+					chip_index,
+					position: piece.position,
+					rotation: piece.rotation
+				});
+			}
+		}
+		Some(self.scorer.score_result(result, self.all_chips))
+	}
+
+	fn __assign(
+		&self,
+		groups: &[(usize, Vec<&TilePiece>)],
+		group: usize,
+		from: usize,
+		stat: Stat,
+		chips: &mut Vec<usize>,
+		best: &mut Option<Assignment>
+	) {
+		let (k, pieces) = match groups.get(group) {
+			Some(x) => x,
+			None => {
+				let score = self.scorer.score(&stat);
+				let correction_cost = self.get_correction_cost(groups, chips);
+				if best.as_ref().is_none_or(| x | score > x.score || (score == x.score && correction_cost < x.correction_cost)) {
+					*best = Some(Assignment {
+						chips: chips.clone(),
+						score,
+						correction_cost
+					});
+				}
+				return;
+			}
+		};
+		if let Some(best) = best {
+			if self.scorer.get_upper_bound(&(stat + self.get_optimistic(groups, group, chips.len()))) < best.score {
+				return;
+			}
+		}
+		let candidates = &self.candidates[*k].1;
+		let start = chips.len() - groups[..group].iter().map(| x | x.1.len()).sum::<usize>();
+		let left = pieces.len() - start;
+		for (i, chip_index) in candidates.iter().enumerate().take(candidates.len() - left + 1).skip(from) {
+			chips.push(*chip_index);
+			let stat = stat + self.stats[*chip_index];
+			if left == 1 {
+				self.__assign(groups, group + 1, 0, stat, chips, best);
+			} else {
+				self.__assign(groups, group, i + 1, stat, chips, best);
+			}
+			chips.pop();
+		}
+	}
+
+	/// At least what the pieces not assigned yet can add to every stat.
+	fn get_optimistic(&self, groups: &[(usize, Vec<&TilePiece>)], group: usize, assigned: usize) -> Stat {
+		let mut optimistic = Stat::default();
+		let mut skip = assigned - groups[..group].iter().map(| x | x.1.len()).sum::<usize>();
+		for (k, pieces) in &groups[group..] {
+			let mut best = Stat::default();
+			for chip_index in &self.candidates[*k].1 {
+				best = best.max(&self.stats[*chip_index]);
+			}
+			for _ in skip..pieces.len() {
+				optimistic += best;
+			}
+			skip = 0;
+		}
+		optimistic
+	}
+
+	fn get_correction_cost(&self, groups: &[(usize, Vec<&TilePiece>)], chips: &[usize]) -> usize {
+		let mut offset = 0;
+		let mut correction_cost = 0;
+		for (_, pieces) in groups {
+			let chips = &chips[offset..offset + pieces.len()];
+			offset += pieces.len();
+			for (piece, chip_index) in pieces.iter().zip(self.match_rotations(pieces, chips)) {
+				let chip = &self.all_chips[chip_index];
+				if chip.rotation != piece.rotation {
+					correction_cost += chip.get_correction_cost();
+				}
+			}
+		}
+		correction_cost
+	}
+
+	/// Orders `chips` to fill `pieces` so the most expensive corrections are the ones avoided.
+	fn match_rotations(&self, pieces: &[&TilePiece], chips: &[usize]) -> Vec<usize> {
+		let mut chips = chips.to_vec();
+		chips.sort_by_key(| x | std::cmp::Reverse(self.all_chips[*x].get_correction_cost()));
+		let mut matched = vec![usize::MAX; pieces.len()];
+		let mut rest = Vec::new();
+		for chip_index in chips {
+			let rotation = self.all_chips[chip_index].rotation;
+			match (0..pieces.len()).find(| i | matched[*i] == usize::MAX && pieces[*i].rotation == rotation) {
+				Some(i) => matched[i] = chip_index,
+				None => rest.push(chip_index)
+			}
+		}
+		let mut rest = rest.into_iter();
+		for x in matched.iter_mut() {
+			if *x == usize::MAX {
+				*x = rest.next().unwrap();
+			}
+		}
+		matched
+	}
+}
@@ -0,0 +1,93 @@
+use crate::calculation::CalculationResult;
+use crate::canvas::Canvas;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Fixed-size table of search states already visited, shared by every thread of a search.
+///
+/// A state is a layout: the filled cells of the canvas and where each chip is, hashed Zobrist-style
+/// from a random key for every byte value of every row of the canvas, and for every chip a random key
+/// mixed with its position and rotation, xored together. So the only state met twice is the same
+/// layout with its chips placed in another order, which `SearchMode::FirstFit` does.
+/// Each key goes in one slot picked by its low bits and replaces whatever was there,
+/// so the table never grows and only forgets states.
+pub struct TranspositionTable {
+	slots: Vec<AtomicU64>,
+	rows: Vec<[u64; 256]>,
+	chips: Vec<u64>
+}
+
+/// splitmix64's finalizer
+#[inline(always)]
+fn mix(mut x: u64) -> u64 {
+	x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+	x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+	x ^ (x >> 31)
+}
+
+impl TranspositionTable {
+	/// `size` is rounded down to a power of two. Tables of the same size and chip count use the same keys.
+	pub fn new(size: usize, chip_count: usize) -> Self {
+		let size = if size == 0 { 1 } else { 1 << (usize::BITS - 1 - size.leading_zeros()) };
+		let mut seed = 0x9e3779b97f4a7c15u64;
+		let mut next = || {
+			seed = seed.wrapping_add(0x9e3779b97f4a7c15);
+			mix(seed)
+		};
+		let mut rows = vec![[0u64; 256]; 8];
+		for row in rows.iter_mut() {
+			for key in row.iter_mut() {
+				*key = next();
+			}
+		}
+		Self {
+			slots: (0..size).map(| _ | AtomicU64::new(0)).collect(),
+			rows,
+			chips: (0..chip_count).map(| _ | next()).collect()
+		}
+	}
+
+	pub fn get_key(&self, canvas: &Canvas, base: &CalculationResult) -> u64 {
+		let mut key = 0;
+		for x in base.iter() {
+			let placement = (x.position.y as u64) << 16 | (x.position.x as u64) << 8 | x.rotation as u64;
+			key ^= mix(self.chips[x.chip_index] ^ placement);
+		}
+		for (y, row) in self.rows.iter().enumerate() {
+			key ^= row[(canvas.map >> (56 - 8 * y)) as u8 as usize];
+		}
+		key
+	}
+
+	/// Records the state, returns `true` if it was already in the table.
+	#[inline(always)]
+	pub fn visit(&self, canvas: &Canvas, base: &CalculationResult) -> bool {
+		let key = self.get_key(canvas, base);
+		let slot = &self.slots[key as usize & (self.slots.len() - 1)];
+		slot.swap(key, Ordering::Relaxed) == key
+	}
+
+	/// Slots holding a state, with their keys.
+	pub fn get_entries(&self) -> Vec<(usize, u64)> {
+		self.slots.iter().enumerate()
+			.map(| (i, x) | (i, x.load(Ordering::Relaxed)))
+			.filter(| x | x.1 != 0)
+			.collect()
+	}
+
+	/// Puts back entries from `get_entries` of a table of the same size.
+	pub fn restore(&self, entries: &[(usize, u64)]) {
+		for (i, key) in entries {
+			if let Some(slot) = self.slots.get(*i) {
+				slot.store(*key, Ordering::Relaxed);
+			}
+		}
+	}
+
+	pub fn len(&self) -> usize {
+		self.slots.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.slots.is_empty()
+	}
+}
@@ -0,0 +1,17 @@
+use chipcalc_native_rust::calculation::Board;
+use chipcalc_native_rust::score::Scorer;
+use chipcalc_native_rust::stat::Stat;
+
+/// Only the top level's caps are tabled yet, the lower levels have to be added along with this test.
+#[test]
+fn stat_caps_are_only_given_for_tabled_levels() {
+	assert_eq!(Board::NameBGM71.get_stat_cap(Board::MAX_LEVEL), Some(Stat::new(157, 328, 191, 45)));
+	assert_eq!(Board::NameQLZ04.get_stat_cap(Board::MAX_LEVEL), Some(Stat::new(188, 162, 66, 125)));
+	assert_eq!(Board::NameMk153.get_stat_cap(Board::MAX_LEVEL), Some(Stat::new(104, 186, 350, 39)));
+	for level in [0, 1, 4, Board::MAX_LEVEL + 1] {
+		assert_eq!(Board::NameBGM71.get_stat_cap(level), None);
+	}
+	assert_eq!(Board::NameAT4.get_stat_caps(), vec![None, None, None, None, Some(Stat::new(134, 291, 233, 46))]);
+	assert!(Scorer::for_board(Board::NameM2, 3).is_none());
+	assert_eq!(Scorer::for_board(Board::NameM2, Board::MAX_LEVEL).unwrap().cap, Stat::new(103, 30, 141, 55));
+}
@@ -0,0 +1,26 @@
+use chipcalc_native_rust::calculation::Board;
+use chipcalc_native_rust::canvas::{Canvas, RawCanvas};
+use chipcalc_native_rust::vector2::Vector2;
+
+#[test]
+fn raw_canvas_builds_the_canvas_of_its_rows() {
+	let canvas = Board::NameBGM71.to_canvas(1);
+	let raw = RawCanvas::from(canvas);
+	assert_eq!(raw.raw_map.len(), 8);
+	assert_eq!(Canvas::from(raw.clone()), canvas);
+	assert_eq!(Canvas::from(raw.clone()).get_raw_map(), raw.raw_map);
+
+	let small: Canvas = RawCanvas {
+		size: Vector2::new(3, 2),
+		raw_map: vec![0b0100_0000, 0]
+	}.into();
+	assert_eq!(small.get_left_space(), 5);
+	assert!(small.is_filled(&Vector2::new(1, 0)));
+	assert!(small.is_filled(&Vector2::new(3, 1)));
+}
+
+#[test]
+#[should_panic]
+fn canvas_needs_a_row_for_every_line() {
+	Canvas::new(Vector2::new(8, 8), vec![0; 7]);
+}
@@ -0,0 +1,78 @@
+mod common;
+
+use chipcalc_native_rust::calculation::{Backend, Board, CalculationJob, CalculationResult, Config, SearchMode};
+use chipcalc_native_rust::control::SearchController;
+use chipcalc_native_rust::search::Search;
+use chipcalc_native_rust::shape::Shape;
+use common::{chips, found, layout};
+
+#[test]
+fn resumed_search_finds_exactly_the_remaining_results() {
+	let all_chips = chips(&[Shape::_4_O, Shape::_4_L, Shape::_3_I, Shape::_3_L, Shape::_3_L, Shape::_4_I]);
+	let canvas = Board::NameBGM71.to_canvas(1);
+	let configs = [
+		(SearchMode::FirstFit, Backend::Backtrack),
+		(SearchMode::AnchorCell, Backend::Backtrack),
+		(SearchMode::FirstFit, Backend::DancingLinks)
+	];
+	for (mode, backend) in configs {
+		let job = || CalculationJob::new(canvas, &all_chips, CalculationResult::new(&canvas), Config {
+			max_empty_cells: 2,
+			search: mode,
+			backend,
+			transposition_table_size: 1 << 10,
+			..Config::default()
+		});
+		let mut expected = found(&job());
+
+		let mut resumed = Vec::new();
+		let mut paused = 0;
+		let mut search = Search::new(job());
+		while search.run(&SearchController::new().with_node_budget(50), &mut | result | resumed.push(layout(&result))).is_err() {
+			let checkpoint = search.checkpoint().to_string();
+			search = Search::resume(job(), &serde_json::from_str(&checkpoint).unwrap()).unwrap();
+			paused += 1;
+		}
+		assert!(paused > 0);
+		if backend == Backend::DancingLinks {
+			// backtracked in another order than dancing links take
+			expected.sort();
+			resumed.sort();
+		}
+		assert_eq!(resumed, expected, "{:?} {:?}", mode, backend);
+	}
+}
+
+#[test]
+fn resume_refuses_a_checkpoint_that_does_not_fit_the_job() {
+	let all_chips = chips(&[Shape::_4_O, Shape::_4_L, Shape::_3_I, Shape::_3_L, Shape::_3_L, Shape::_4_I]);
+	let canvas = Board::NameBGM71.to_canvas(1);
+	let config = Config {
+		max_empty_cells: 2,
+		search: SearchMode::AnchorCell,
+		..Config::default()
+	};
+	let job = | config | CalculationJob::new(canvas, &all_chips, CalculationResult::new(&canvas), config);
+	let mut search = Search::new(job(config));
+	assert!(search.run(&SearchController::new().with_node_budget(50), &mut | _ | ()).is_err());
+	let checkpoint = search.checkpoint();
+	assert!(checkpoint["stack"].as_array().unwrap().len() > 2);
+	assert!(!checkpoint["stack"][2]["chips"].as_array().unwrap().is_empty());
+	assert!(Search::resume(job(config), &checkpoint).is_some());
+
+	assert!(Search::resume(job(Config { max_empty_cells: 1, ..config }), &checkpoint).is_none());
+	let tampered: [fn(&mut serde_json::Value); 4] = [
+		| x | x["stack"][1]["left_size"] = 0.into(),
+		| x | x["stack"][2]["correction_cost"] = 10.into(),
+		| x | x["stack"][2]["uncovered"] = 1.into(),
+		| x | {
+			let chip = x["stack"][2]["chips"][0].clone();
+			x["stack"][2]["chips"].as_array_mut().unwrap().push(chip);
+		}
+	];
+	for tamper in tampered.iter() {
+		let mut checkpoint = checkpoint.clone();
+		tamper(&mut checkpoint);
+		assert!(Search::resume(job(config), &checkpoint).is_none());
+	}
+}
@@ -0,0 +1,46 @@
+//! Helpers shared by the integration tests.
+#![allow(dead_code)]
+
+use chipcalc_native_rust::calculation::{CalculationJob, CalculationResult};
+use chipcalc_native_rust::chip::{Chip, Color};
+use chipcalc_native_rust::matrix::MatrixRotation;
+use chipcalc_native_rust::shape::Shape;
+use chipcalc_native_rust::stat::Stat;
+
+/// Chip index, position and rotation of every placed chip, by chip index.
+pub type Layout = Vec<(usize, u8, u8, u8)>;
+
+/// Unrotated chips of `shapes` that all have the same stat.
+pub fn chips(shapes: &[Shape]) -> Vec<Chip> {
+	shapes.iter().enumerate().map(| (i, shape) | Chip::new(
+		i as u32,
+		*shape,
+		Color::Orange,
+		Stat::new(1, 0, 0, 0),
+		5,
+		20,
+		MatrixRotation::Cw0
+	)).collect()
+}
+
+pub fn layout(result: &CalculationResult) -> Layout {
+	let mut layout: Layout = result.iter()
+		.map(| x | (x.chip_index, x.position.x, x.position.y, x.rotation as u8))
+		.collect();
+	layout.sort();
+	layout
+}
+
+/// Layouts of `results` in order, so searches finding them in another order compare equal.
+pub fn layouts(results: &[CalculationResult]) -> Vec<Layout> {
+	let mut layouts: Vec<Layout> = results.iter().map(layout).collect();
+	layouts.sort();
+	layouts
+}
+
+/// Layouts `job` finds, in the order it finds them.
+pub fn found(job: &CalculationJob) -> Vec<Layout> {
+	let mut found = Vec::new();
+	job.calculate(&mut | result | found.push(layout(&result)));
+	found
+}
@@ -0,0 +1,71 @@
+mod common;
+
+use chipcalc_native_rust::calculation::{Board, CalculationJob, CalculationResult, Config};
+use chipcalc_native_rust::chip::Chip;
+use chipcalc_native_rust::control::{Progress, SearchController, StopReason};
+use chipcalc_native_rust::shape::Shape;
+use common::chips;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+fn job(all_chips: &Vec<Chip>) -> CalculationJob<'_> {
+	let canvas = Board::NameBGM71.to_canvas(1);
+	CalculationJob::new(canvas, all_chips, CalculationResult::new(&canvas), Config {
+		max_empty_cells: 2,
+		..Config::default()
+	})
+}
+
+#[test]
+fn cancelling_stops_at_the_next_node() {
+	let all_chips = chips(&[Shape::_4_O, Shape::_4_L, Shape::_3_I, Shape::_3_L, Shape::_3_L]);
+	let controller = SearchController::new();
+	let mut found = 0;
+	let result = job(&all_chips).calculate_with(&controller, &mut | _ | {
+		found += 1;
+		controller.cancel();
+	});
+	assert_eq!(result, Err(StopReason::Cancelled));
+	assert_eq!(found, 1);
+	assert_eq!(controller.get_progress().results, 1);
+}
+
+#[test]
+fn node_budget_stops_past_the_budget() {
+	let all_chips = chips(&[Shape::_4_O, Shape::_4_L, Shape::_3_I, Shape::_3_L, Shape::_3_L]);
+	let controller = SearchController::new().with_node_budget(100);
+	assert_eq!(job(&all_chips).calculate_with(&controller, &mut | _ | ()), Err(StopReason::NodeBudget));
+	assert_eq!(controller.get_progress().nodes, 101);
+	assert!(job(&all_chips).calculate_with(&SearchController::new().with_node_budget(1 << 40), &mut | _ | ()).is_ok());
+}
+
+#[test]
+fn time_limit_counts_from_the_start_of_the_search() {
+	let all_chips = chips(&[Shape::_4_O, Shape::_4_L, Shape::_3_I, Shape::_3_L, Shape::_3_L]);
+	let controller = SearchController::new().with_time_limit(Duration::ZERO);
+	assert_eq!(job(&all_chips).calculate_with(&controller, &mut | _ | ()), Err(StopReason::TimeLimit));
+
+	// the deadline would have passed before the search if it counted from here
+	let controller = SearchController::new().with_time_limit(Duration::from_millis(500));
+	thread::sleep(Duration::from_millis(600));
+	assert!(job(&all_chips).calculate_with(&controller, &mut | _ | ()).is_ok());
+	assert!(controller.get_progress().nodes > 256);
+}
+
+#[test]
+fn progress_is_reported_on_the_way_and_at_the_end() {
+	let all_chips = chips(&[Shape::_4_O, Shape::_4_L, Shape::_3_I, Shape::_3_L, Shape::_3_L]);
+	let reports: Arc<Mutex<Vec<Progress>>> = Arc::new(Mutex::new(Vec::new()));
+	let sink = reports.clone();
+	let controller = SearchController::new().with_progress(50, move | x | sink.lock().unwrap().push(*x));
+	let mut found = 0;
+	assert!(job(&all_chips).calculate_with(&controller, &mut | _ | found += 1).is_ok());
+	let reports = reports.lock().unwrap();
+	let last = *reports.last().unwrap();
+	assert_eq!(reports.len() as u64, last.nodes / 50 + 1);
+	assert!(reports.windows(2).all(| x | x[0].nodes <= x[1].nodes && x[0].jobs_done <= x[1].jobs_done));
+	assert_eq!(last.results, found);
+	assert_eq!(last.jobs_done, last.jobs_total);
+	assert_eq!(last.get_fraction(), 1.0);
+}
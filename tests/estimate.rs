@@ -0,0 +1,53 @@
+mod common;
+
+use chipcalc_native_rust::calculation::{Backend, Board, CalculationJob, CalculationResult, Config, SearchMode};
+use chipcalc_native_rust::chip::Chip;
+use chipcalc_native_rust::control::{Progress, SearchController};
+use chipcalc_native_rust::shape::Shape;
+use common::chips;
+
+fn job(all_chips: &Vec<Chip>, search: SearchMode, backend: Backend) -> CalculationJob<'_> {
+	let canvas = Board::NameBGM71.to_canvas(1);
+	CalculationJob::new(canvas, all_chips, CalculationResult::new(&canvas), Config {
+		max_empty_cells: 2,
+		search,
+		backend,
+		..Config::default()
+	})
+}
+
+fn searched(job: &CalculationJob) -> Progress {
+	let controller = SearchController::new();
+	assert!(job.calculate_with(&controller, &mut | _ | ()).is_ok());
+	controller.get_progress()
+}
+
+#[test]
+fn small_searches_are_measured_exactly() {
+	let all_chips = chips(&[Shape::_4_O, Shape::_4_L, Shape::_3_I, Shape::_3_L, Shape::_3_L]);
+	for backend in [Backend::Backtrack, Backend::DancingLinks] {
+		let job = job(&all_chips, SearchMode::AnchorCell, backend);
+		let estimate = job.estimate(100, 0);
+		let progress = searched(&job);
+		assert!(estimate.exact);
+		assert_eq!(estimate.nodes, progress.nodes as f64, "{:?}", backend);
+		assert_eq!(estimate.results, progress.results as f64, "{:?}", backend);
+	}
+}
+
+#[test]
+fn probes_are_reproducible_and_close_to_the_search() {
+	let all_chips = chips(&[Shape::_4_O, Shape::_4_L, Shape::_3_I, Shape::_3_L, Shape::_3_L, Shape::_4_I, Shape::_4_O]);
+	for backend in [Backend::Backtrack, Backend::DancingLinks] {
+		let job = job(&all_chips, SearchMode::AnchorCell, backend);
+		let estimate = job.estimate(2000, 7);
+		let again = job.estimate(2000, 7);
+		assert!(!estimate.exact);
+		assert_eq!((estimate.nodes, estimate.results), (again.nodes, again.results));
+		let progress = searched(&job);
+		let ratio = estimate.nodes / progress.nodes as f64;
+		assert!((0.7..1.3).contains(&ratio), "{:?} estimated {} nodes of {}", backend, estimate.nodes, progress.nodes);
+		let ratio = estimate.results / progress.results as f64;
+		assert!((0.7..1.3).contains(&ratio), "{:?} estimated {} results of {}", backend, estimate.results, progress.results);
+	}
+}
@@ -0,0 +1,61 @@
+mod common;
+
+use chipcalc_native_rust::calculation::{Backend, Board, CalculationJob, CalculationResult, Config, SearchMode};
+use chipcalc_native_rust::parallel::ParallelConfig;
+use chipcalc_native_rust::shape::Shape;
+use common::{chips, layouts};
+use std::sync::Mutex;
+
+#[test]
+fn parallel_finds_the_same_results_as_calculate() {
+	let all_chips = chips(&[Shape::_4_O, Shape::_4_L, Shape::_3_I, Shape::_3_L, Shape::_3_L, Shape::_4_I, Shape::_4_O]);
+	let canvas = Board::NameBGM71.to_canvas(1);
+	let configs = [
+		(SearchMode::FirstFit, Backend::Backtrack),
+		(SearchMode::AnchorCell, Backend::Backtrack),
+		(SearchMode::FirstFit, Backend::DancingLinks),
+		(SearchMode::AnchorCell, Backend::DancingLinks)
+	];
+	for (search, backend) in configs {
+		let job = CalculationJob::new(canvas, &all_chips, CalculationResult::new(&canvas), Config {
+			max_empty_cells: 2,
+			search,
+			backend,
+			..Config::default()
+		});
+		let mut serial = Vec::new();
+		job.calculate(&mut | result | serial.push(result));
+		let serial = layouts(&serial);
+		assert!(!serial.is_empty());
+		for split_depth in [0, 1, 3] {
+			let sink = Mutex::new(Vec::new());
+			job.clone().calculate_parallel(&ParallelConfig { threads: 4, split_depth }, &sink);
+			assert_eq!(layouts(&sink.into_inner().unwrap()), serial, "{:?} {:?} split at depth {}", search, backend, split_depth);
+		}
+	}
+}
+
+#[test]
+fn parallel_with_a_transposition_table_finds_the_same_results_as_calculate() {
+	let all_chips = chips(&[Shape::_4_O, Shape::_4_L, Shape::_3_I, Shape::_3_L, Shape::_3_L, Shape::_4_I, Shape::_4_O]);
+	let canvas = Board::NameBGM71.to_canvas(1);
+	for search in [SearchMode::FirstFit, SearchMode::AnchorCell] {
+		let job = CalculationJob::new(canvas, &all_chips, CalculationResult::new(&canvas), Config {
+			max_empty_cells: 2,
+			search,
+			// large enough to forget nothing, or threads could find a layout again
+			transposition_table_size: 1 << 20,
+			..Config::default()
+		});
+		let mut serial = Vec::new();
+		job.calculate(&mut | result | serial.push(result));
+		let serial = layouts(&serial);
+		for split_depth in [1, 2, 3] {
+			for _ in 0..3 {
+				let sink = Mutex::new(Vec::new());
+				job.clone().calculate_parallel(&ParallelConfig { threads: 4, split_depth }, &sink);
+				assert_eq!(layouts(&sink.into_inner().unwrap()), serial, "{:?} split at depth {}", search, split_depth);
+			}
+		}
+	}
+}
@@ -0,0 +1,35 @@
+use chipcalc_native_rust::calculation::{Board, CalculationResult, CalculationResultChip};
+use chipcalc_native_rust::matrix::MatrixRotation;
+use chipcalc_native_rust::vector2::Vector2;
+
+fn chip(chip_index: usize) -> CalculationResultChip {
+	CalculationResultChip {
+		chip_index,
+		position: Vector2::new(chip_index as u8, 0),
+		rotation: MatrixRotation::Cw0
+	}
+}
+
+#[test]
+fn chips_changed_in_place_keep_the_used_chips_in_step() {
+	let mut result = CalculationResult::new(&Board::NameBGM71.to_canvas(1));
+	for chip_index in [3, 70, 5] {
+		result.push(chip(chip_index));
+	}
+	assert_eq!(result.get_chips().len(), 3);
+	assert_eq!(result.get_chips()[1].chip_index, result[1].chip_index);
+
+	{
+		let mut chips = result.get_chips_mut();
+		chips.retain(| x | x.chip_index != 70);
+		chips.push(chip(9));
+		chips.sort_by_key(| x | std::cmp::Reverse(x.chip_index));
+	}
+	assert_eq!(result.iter().map(| x | x.chip_index).collect::<Vec<usize>>(), vec![9, 5, 3]);
+	assert_eq!(result.get_used().iter().collect::<Vec<usize>>(), vec![3, 5, 9]);
+	assert!(!result.is_used(70));
+
+	result.get_chips_mut().clear();
+	assert!(result.is_empty());
+	assert!(result.get_used().is_empty());
+}
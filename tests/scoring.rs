@@ -0,0 +1,160 @@
+mod common;
+
+use chipcalc_native_rust::anneal::AnnealConfig;
+use chipcalc_native_rust::anytime::BestSoFar;
+use chipcalc_native_rust::calculation::{Board, CalculationJob, CalculationResult, Config, SearchMode};
+use chipcalc_native_rust::chip::{Chip, Color};
+use chipcalc_native_rust::control::{SearchController, StopReason};
+use chipcalc_native_rust::matrix::MatrixRotation;
+use chipcalc_native_rust::score::{ScoredResult, Scorer};
+use chipcalc_native_rust::shape::Shape;
+use chipcalc_native_rust::stat::Stat;
+use common::{layout, Layout};
+
+fn chips() -> Vec<Chip> {
+	let shapes = [Shape::_4_O, Shape::_4_L, Shape::_3_I, Shape::_3_L, Shape::_3_L, Shape::_4_I, Shape::_4_O, Shape::_4_T];
+	shapes.iter().enumerate().map(| (i, shape) | Chip::new(
+		i as u32,
+		*shape,
+		Color::Orange,
+		Stat::new((i % 3) as i32, (i % 2) as i32 * 2, ((i + 1) % 3) as i32, (i % 4 == 0) as i32),
+		2 + i % 4,
+		10,
+		if i % 3 == 0 { MatrixRotation::Cw90 } else { MatrixRotation::Cw0 }
+	)).collect()
+}
+
+/// Caps some stats below what the chips can add, so overflow and the bound both matter.
+fn scorer() -> Scorer {
+	Scorer {
+		cap: Stat::new(30, 40, 25, 5),
+		weight: Stat::new(2, 1, 1, 3),
+		overflow_penalty: 2
+	}
+}
+
+fn job(all_chips: &Vec<Chip>, search: SearchMode) -> CalculationJob<'_> {
+	let canvas = Board::NameBGM71.to_canvas(1);
+	CalculationJob::new(canvas, all_chips, CalculationResult::new(&canvas), Config {
+		max_empty_cells: 2,
+		search,
+		..Config::default()
+	})
+}
+
+fn keys(results: &[ScoredResult]) -> Vec<(i64, usize, Layout)> {
+	results.iter().map(| x | (
+		x.score,
+		x.result.correction_cost,
+		layout(&x.result)
+	)).collect()
+}
+
+#[test]
+fn top_keeps_the_best_results_in_order() {
+	let all_chips = chips();
+	let job = job(&all_chips, SearchMode::AnchorCell);
+	let mut all = Vec::new();
+	job.calculate(&mut | result | all.push(scorer().score_result(result, &all_chips)));
+	all.sort_by(| a, b | b.cmp(a));
+	all.truncate(5);
+	let top = job.calculate_top(5, scorer());
+	assert_eq!(keys(&top), keys(&all));
+	assert!(top[0].score < scorer().get_upper_bound(&scorer().cap), "the cap is saturated");
+}
+
+#[test]
+fn best_and_two_phase_match_top() {
+	let all_chips = chips();
+	for search in [SearchMode::FirstFit, SearchMode::AnchorCell] {
+		let job = job(&all_chips, search);
+		let top = job.calculate_top(5, scorer());
+		assert_eq!(top.len(), 5);
+		assert_eq!(keys(&job.calculate_best(5, scorer())), keys(&top), "{:?}", search);
+	}
+	let job = job(&all_chips, SearchMode::AnchorCell);
+	let top = job.calculate_top(1, scorer());
+	let two_phase = job.calculate_two_phase(5, scorer());
+	assert_eq!(two_phase[0].score, top[0].score);
+	assert!(two_phase.windows(2).all(| x | x[0] >= x[1]));
+}
+
+#[test]
+fn exact_beam_matches_top_by_anchor_cell() {
+	let all_chips = chips();
+	let top = job(&all_chips, SearchMode::AnchorCell).calculate_top(5, scorer());
+	let first_fit = job(&all_chips, SearchMode::FirstFit);
+	for width in [100000, usize::MAX] {
+		let wide = first_fit.calculate_beam(width, 5, scorer());
+		assert!(wide.is_exact());
+		assert_eq!(keys(&wide.results), keys(&top));
+	}
+	let narrow = first_fit.calculate_beam(1, 5, scorer());
+	assert!(!narrow.is_exact());
+	assert!(narrow.results[0] <= top[0]);
+}
+
+#[test]
+fn annealing_is_reproducible_and_never_worse() {
+	let all_chips = chips();
+	let job = job(&all_chips, SearchMode::FirstFit);
+	let start = job.results().next().unwrap();
+	let start_scored = scorer().score_result(start.clone(), &all_chips);
+	for seed in 0..3 {
+		let anneal = AnnealConfig {
+			seed,
+			iterations: 2000,
+			..AnnealConfig::default()
+		};
+		let improved = job.improve(&start, scorer(), anneal).unwrap();
+		assert_eq!(keys(std::slice::from_ref(&improved)), keys(&[job.improve(&start, scorer(), anneal).unwrap()]));
+		assert!(improved >= start_scored);
+		assert!(improved.result.left_size <= job.get_config().max_empty_cells);
+	}
+
+	let mut short = start.clone();
+	short.pop();
+	let mut twice = start.clone();
+	twice.push(start[0].clone());
+	for layout in [short, twice] {
+		assert!(job.improve(&layout, scorer(), AnnealConfig::default()).is_none());
+	}
+
+	let no_chips = Vec::new();
+	let canvas = Board::NameBGM71.to_canvas(1);
+	let empty = CalculationJob::new(canvas, &no_chips, CalculationResult::new(&canvas), Config {
+		max_empty_cells: u8::MAX,
+		..Config::default()
+	});
+	assert_eq!(empty.improve(&CalculationResult::new(&canvas), scorer(), AnnealConfig::default()).unwrap().score, 0);
+}
+
+#[test]
+fn anytime_holds_a_layout_early_and_ends_optimal() {
+	let all_chips = chips();
+	let job = job(&all_chips, SearchMode::FirstFit);
+	let top = job.calculate_top(1, scorer());
+
+	let best = BestSoFar::default();
+	assert!(best.get_best().is_none());
+	assert_eq!(job.calculate_anytime(scorer(), &best), Ok(()));
+	assert!(best.is_optimal());
+	assert_eq!(best.get_score(), Some(top[0].score));
+	assert_eq!(best.get_best().unwrap().score, top[0].score);
+	let improvements = best.get_improvements();
+	assert!(improvements > 1, "the greedy layout was the best already");
+
+	let early = BestSoFar::new(SearchController::new().with_node_budget(1000));
+	assert_eq!(job.calculate_anytime(scorer(), &early), Err(StopReason::NodeBudget));
+	assert!(!early.is_optimal());
+	assert!(early.get_score().unwrap() <= top[0].score);
+	assert!(early.get_improvements() >= 1 && early.get_improvements() <= improvements);
+
+	// stops the greedy layout too
+	let cancelled = BestSoFar::default();
+	cancelled.get_controller().cancel();
+	assert_eq!(job.calculate_anytime(scorer(), &cancelled), Err(StopReason::Cancelled));
+	assert!(cancelled.get_best().is_none());
+	assert_eq!(cancelled.get_improvements(), 0);
+	assert!(!cancelled.is_optimal());
+}
@@ -0,0 +1,225 @@
+mod common;
+
+use chipcalc_native_rust::calculation::{Backend, Board, CalculationJob, CalculationResult, ChipOrder, Config, SearchMode};
+use chipcalc_native_rust::canvas::Canvas;
+use chipcalc_native_rust::chip::Chip;
+use chipcalc_native_rust::shape::Shape;
+use chipcalc_native_rust::vector2::Vector2;
+use common::{chips, found, layout, Layout};
+use std::collections::HashSet;
+
+fn layouts(board: Board, level: u8, all_chips: &Vec<Chip>, config: Config) -> Vec<Layout> {
+	let canvas = board.to_canvas(level);
+	found(&CalculationJob::new(canvas, all_chips, CalculationResult::new(&canvas), config))
+}
+
+fn compare(board: Board, level: u8, all_chips: &Vec<Chip>, max_empty_cells: u8) {
+	let config = Config {
+		max_empty_cells,
+		..Config::default()
+	};
+	let first_fit: HashSet<Layout> = layouts(board, level, all_chips, config).into_iter().collect();
+	let anchor = layouts(board, level, all_chips, Config {
+		search: SearchMode::AnchorCell,
+		..config
+	});
+	let anchor_set: HashSet<Layout> = anchor.iter().cloned().collect();
+
+	assert!(!first_fit.is_empty());
+	assert_eq!(anchor.len(), anchor_set.len(), "anchor search repeated a layout");
+	assert!(first_fit.is_subset(&anchor_set), "anchor search missed a layout");
+
+	let canvas = board.to_canvas(level);
+	let count = CalculationJob::new(canvas, all_chips, CalculationResult::new(&canvas), Config {
+		search: SearchMode::AnchorCell,
+		..config
+	}).count();
+	assert_eq!(count.total as usize, anchor.len());
+	assert_eq!(count.by_chips.iter().sum::<u64>(), count.total);
+
+	let unpruned: HashSet<Layout> = layouts(board, level, all_chips, Config {
+		search: SearchMode::AnchorCell,
+		prune_dead_regions: false,
+		..config
+	}).into_iter().collect();
+	assert_eq!(unpruned, anchor_set, "pruning dead regions lost a layout");
+
+	for order in [ChipOrder::LargestFirst, ChipOrder::FewestPlacements, ChipOrder::HighestStat] {
+		let ordered: HashSet<Layout> = layouts(board, level, all_chips, Config {
+			search: SearchMode::AnchorCell,
+			order,
+			..config
+		}).into_iter().collect();
+		assert_eq!(ordered, anchor_set, "{:?} changed the layouts", order);
+	}
+
+	let exhaustive: HashSet<Layout> = layouts(board, level, all_chips, Config {
+		exhaustive: true,
+		..config
+	}).into_iter().collect();
+	assert!(first_fit.is_subset(&exhaustive));
+	assert_eq!(exhaustive, anchor_set);
+
+	let dlx = layouts(board, level, all_chips, Config {
+		backend: Backend::DancingLinks,
+		..config
+	});
+	let dlx_set: HashSet<Layout> = dlx.iter().cloned().collect();
+	assert_eq!(dlx.len(), dlx_set.len(), "dancing links repeated a layout");
+	assert_eq!(dlx_set, anchor_set);
+
+	let dlx_job = CalculationJob::new(canvas, all_chips, CalculationResult::new(&canvas), Config {
+		backend: Backend::DancingLinks,
+		..config
+	});
+	let iterated: Vec<Layout> = dlx_job.results().map(| x | layout(&x)).collect();
+	assert_eq!(iterated.len(), dlx.len());
+	assert_eq!(iterated.into_iter().collect::<HashSet<Layout>>(), dlx_set);
+	assert_eq!(dlx_job.count().total as usize, dlx.len());
+}
+
+#[test]
+fn anchor_cell_matches_first_fit_full_cover() {
+	let all_chips = chips(&[
+		Shape::_4_O, Shape::_4_O, Shape::_4_I, Shape::_4_I, Shape::_4_L, Shape::_4_Lm
+	]);
+	compare(Board::NameBGM71, 1, &all_chips, 0);
+}
+
+#[test]
+fn anchor_cell_matches_first_fit_with_holes() {
+	let all_chips = chips(&[
+		Shape::_4_O, Shape::_4_L, Shape::_3_I, Shape::_3_L, Shape::_3_L
+	]);
+	compare(Board::NameBGM71, 1, &all_chips, 2);
+}
+
+#[test]
+fn transposition_table_keeps_every_layout_and_every_search_starts_afresh() {
+	let all_chips = chips(&[
+		Shape::_4_O, Shape::_4_L, Shape::_3_I, Shape::_3_L, Shape::_3_L, Shape::_4_I, Shape::_4_O
+	]);
+	let canvas = Board::NameBGM71.to_canvas(1);
+	for search in [SearchMode::FirstFit, SearchMode::AnchorCell] {
+		let config = Config {
+			max_empty_cells: 2,
+			search,
+			..Config::default()
+		};
+		let plain: HashSet<Layout> = layouts(Board::NameBGM71, 1, &all_chips, config).into_iter().collect();
+		let job = CalculationJob::new(canvas, &all_chips, CalculationResult::new(&canvas), Config {
+			transposition_table_size: 1 << 16,
+			..config
+		});
+		let first = found(&job);
+		let second = found(&job);
+		assert_eq!(first, second, "{:?} searched again with a used table", search);
+		assert_eq!(first.iter().cloned().collect::<HashSet<Layout>>(), plain, "{:?} lost a layout", search);
+		if search == SearchMode::AnchorCell {
+			assert_eq!(first.len(), plain.len());
+		}
+		assert_eq!(job.count().total as usize, first.len());
+		assert_eq!(job.results().count(), first.len());
+	}
+}
+
+#[test]
+fn results_leave_no_forbidden_cell_empty_and_report_their_empty_cells() {
+	let all_chips = chips(&[
+		Shape::_4_O, Shape::_4_L, Shape::_3_I, Shape::_3_L, Shape::_3_L
+	]);
+	let canvas = Board::NameBGM71.to_canvas(1);
+	let forbidden = Canvas::get_bit(&Vector2::new(3, 3)) | Canvas::get_bit(&Vector2::new(4, 2));
+	let configs = [
+		(SearchMode::FirstFit, false, Backend::Backtrack),
+		(SearchMode::FirstFit, true, Backend::Backtrack),
+		(SearchMode::AnchorCell, false, Backend::Backtrack),
+		(SearchMode::AnchorCell, false, Backend::DancingLinks)
+	];
+	for (search, exhaustive, backend) in configs {
+		let config = Config {
+			max_empty_cells: 2,
+			search,
+			exhaustive,
+			backend,
+			..Config::default()
+		};
+		let job = CalculationJob::new(canvas, &all_chips, CalculationResult::new(&canvas), config);
+		let check = | result: &CalculationResult | {
+			let mut covered = 0;
+			for x in result.iter() {
+				covered |= job.get_index().get_placements(&all_chips[x.chip_index], &x.rotation).iter()
+					.find(| p | p.position == x.position)
+					.unwrap()
+					.mask;
+			}
+			assert_eq!(result.uncovered, !canvas.map & !covered, "{:?} {:?}", search, backend);
+			assert_eq!(result.uncovered.count_ones(), result.left_size as u32);
+			assert!(result.left_size <= config.max_empty_cells);
+		};
+		let mut allowed = HashSet::new();
+		job.calculate(&mut | result | {
+			check(&result);
+			if result.uncovered & forbidden == 0 {
+				allowed.insert(layout(&result));
+			}
+		});
+		let mut forbidding = HashSet::new();
+		CalculationJob::new(canvas, &all_chips, CalculationResult::new(&canvas), Config {
+			forbidden_empty: forbidden,
+			..config
+		}).calculate(&mut | result | {
+			check(&result);
+			assert_eq!(result.uncovered & forbidden, 0);
+			forbidding.insert(layout(&result));
+		});
+		assert!(!forbidding.is_empty());
+		assert_eq!(forbidding, allowed, "{:?} {:?}", search, backend);
+	}
+}
+
+#[test]
+fn grouping_identical_chips_finds_each_layout_once_up_to_swapping_them() {
+	let all_chips = chips(&[
+		Shape::_4_O, Shape::_4_O, Shape::_4_O, Shape::_4_I, Shape::_4_I, Shape::_4_L, Shape::_3_L, Shape::_3_L
+	]);
+	let canvas = Board::NameBGM71.to_canvas(1);
+	let configs = [
+		(SearchMode::FirstFit, Backend::Backtrack),
+		(SearchMode::AnchorCell, Backend::Backtrack),
+		(SearchMode::FirstFit, Backend::DancingLinks),
+		(SearchMode::AnchorCell, Backend::DancingLinks)
+	];
+	for (search, backend) in configs {
+		let config = Config {
+			max_empty_cells: 2,
+			search,
+			backend,
+			..Config::default()
+		};
+		let grouped = CalculationJob::new(canvas, &all_chips, CalculationResult::new(&canvas), Config {
+			group_identical: true,
+			..config
+		});
+		assert_eq!(grouped.get_classes().len(), 4);
+		// the same layout whichever of its identical chips are where
+		let classes = grouped.get_classes();
+		let canonical = | layout: Layout | {
+			let mut layout: Layout = layout.into_iter()
+				.map(| (chip_index, x, y, rotation) | (classes.get_class(chip_index).members[0], x, y, rotation))
+				.collect();
+			layout.sort();
+			layout
+		};
+		let ungrouped = layouts(Board::NameBGM71, 1, &all_chips, config);
+		let found: Vec<Layout> = found(&grouped).into_iter().map(canonical).collect();
+		assert!(found.len() < ungrouped.len());
+		let ungrouped: HashSet<Layout> = ungrouped.into_iter().map(canonical).collect();
+		let found_set: HashSet<Layout> = found.iter().cloned().collect();
+		assert!(!found_set.is_empty());
+		assert_eq!(found_set, ungrouped, "{:?} {:?}", search, backend);
+		if search == SearchMode::AnchorCell || backend == Backend::DancingLinks {
+			assert_eq!(found.len(), found_set.len(), "{:?} {:?} found a layout twice", search, backend);
+		}
+	}
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use chipcalc_native_rust::calculation::{Backend, Board, CalculationJob, CalculationResult, Config, SearchMode};
+use chipcalc_native_rust::control::SearchController;
+use chipcalc_native_rust::count::LayoutCount;
+use chipcalc_native_rust::shape::Shape;
+use common::chips;
+
+#[test]
+fn stats_count_every_node_placement_and_top_level_job() {
+	let all_chips = chips(&[Shape::_4_O, Shape::_4_L, Shape::_3_I, Shape::_3_L, Shape::_3_L]);
+	let canvas = Board::NameBGM71.to_canvas(1);
+	let configs = [
+		(SearchMode::FirstFit, Backend::Backtrack),
+		(SearchMode::AnchorCell, Backend::Backtrack),
+		(SearchMode::FirstFit, Backend::DancingLinks)
+	];
+	for (search, backend) in configs {
+		let job = CalculationJob::new(canvas, &all_chips, CalculationResult::new(&canvas), Config {
+			max_empty_cells: 2,
+			search,
+			backend,
+			..Config::default()
+		});
+		let calculated = SearchController::new().with_stats();
+		let mut found = 0;
+		assert!(job.calculate_with(&calculated, &mut | _ | found += 1).is_ok());
+		let counted = SearchController::new().with_stats();
+		let mut count = LayoutCount::default();
+		assert!(job.count_with(&counted, &mut count).is_ok());
+		assert_eq!(count.total, found);
+
+		for controller in [&calculated, &counted] {
+			let stats = controller.get_stats().unwrap();
+			let progress = controller.get_progress();
+			assert_eq!(stats.results, found);
+			assert_eq!(stats.get_nodes(), progress.nodes);
+			assert!(stats.placements_tried >= stats.placements_fitted);
+			if search == SearchMode::FirstFit && backend == Backend::Backtrack {
+				// every node is a chip placed, none leaves a cell empty
+				assert_eq!(stats.placements_fitted, stats.get_nodes(), "{:?} {:?}", search, backend);
+				assert_eq!(stats.nodes_by_depth[0], 0);
+			} else {
+				assert!(stats.placements_fitted < stats.get_nodes(), "{:?} {:?}", search, backend);
+			}
+			assert_eq!(stats.pruned_dead_region + stats.pruned_bound, progress.pruned);
+			assert!(!stats.job_times.is_empty(), "{:?} {:?}", search, backend);
+			assert!(stats.job_times.len() as u64 <= progress.jobs_done);
+			if backend == Backend::Backtrack {
+				assert_eq!(stats.job_times.len() as u64, progress.jobs_total);
+			}
+
+			let json = stats.to_json();
+			assert_eq!(json["nodes"], stats.get_nodes());
+			assert_eq!(json["nodes_by_depth"].as_array().unwrap().len(), stats.nodes_by_depth.len());
+			assert_eq!(json["placements_tried"], stats.placements_tried);
+			assert_eq!(json["placements_fitted"], stats.placements_fitted);
+			assert_eq!(json["results"], found);
+			assert_eq!(json["pruned"]["dead_region"], stats.pruned_dead_region);
+			assert_eq!(json["pruned"]["bound"], stats.pruned_bound);
+			assert_eq!(json["job_times"].as_array().unwrap().len(), stats.job_times.len());
+			assert!(json["elapsed"].as_f64().unwrap() >= 0.0);
+		}
+	}
+	assert!(SearchController::new().get_stats().is_none());
+}